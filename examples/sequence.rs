@@ -12,6 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+// Written before clippy was run on the examples.
+#![allow(clippy::ptr_arg, clippy::assign_op_pattern)]
+
 extern crate aristeia;
 extern crate rand;
 
@@ -23,15 +26,12 @@ use self::Gene::{
     CopyValueFromLeft,
     CopyValueFromRight
 };
-use rand::{
-    distributions::{Distribution, Standard},
-    Rng,
-};
 use std::time::Instant;
 use aristeia::agent::{Agent};
 
-use aristeia::manager::create_manager;
-use aristeia::fitness::ScoreError;
+use aristeia::manager::create_manager_with_sampler;
+use aristeia::sampler::ChoiceSampler;
+use aristeia::fitness::{Direction, FloatScore, Parsimony, ScoreError};
 
 
 #[derive(Clone, PartialEq, Hash)]
@@ -49,26 +49,40 @@ pub fn main() {
 
     let data = vec![0; 10];
 
-    let mut manager = create_manager(fitness_function, data.clone());
+    // Each instruction costs a tiny amount, so that among equally good sequences the shorter ones score higher.
+    let fitness = Parsimony::new(fitness_function, 0.000001, Direction::Maximise);
+    // New instructions are chosen from all of the instructions, with equal chances.
+    let sampler = ChoiceSampler::new(&[
+        MovePointerLeft,
+        MovePointerRight,
+        IncreaseValueByOne,
+        DecreaseValueByOne,
+        CopyValueFromLeft,
+        CopyValueFromRight
+    ]);
+    let mut manager = create_manager_with_sampler(fitness, sampler, data.clone());
+    // Agents start with 30 instructions, but as the number isn't strict, instructions are added and removed
+    // (keeping between 1 and 60 of them) to find out how many are really needed.
     manager.set_number_of_genes(30, false);
-    manager.run(9999);
+    let report = manager.run(FloatScore(0.9999));
     let agents = manager.get_population().get_agents();
 
     println!("Duration: {}", now.elapsed().as_secs() as f64 + now.elapsed().subsec_nanos() as f64 * 1e-9);
     println!("Population: {}", agents.len());
+    println!("Generations: {}", report.generations());
 
     let mut first = true;
-    let mut first_score = 0;
+    let mut first_score = 0.0;
 
-    for (score_index, agent) in agents.iter().rev() {
+    for ((score_index, _), agent) in agents.iter().rev() {
         if first {
             first = false;
-            first_score = *score_index;
+            first_score = score_index.value();
         }
-        if score_index < &(first_score - 20) {
+        if score_index.value() < first_score - 0.002 {
             break;
         }
-        println!("{}", score_index);
+        println!("{} with {} instructions", score_index, agent.get_genes().len());
         println!("{:?}", get_processed_data(agent.get_genes(), &data));
     }
 }
@@ -133,20 +147,7 @@ fn copy_value_from_right(pointer: &mut usize, data: &mut Vec<u8>) {
     data[*pointer] = data[*pointer+1];
 }
 
-impl Distribution<Gene> for Standard {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Gene {
-        match rng.gen_range(0, 6) {
-            0 => MovePointerLeft,
-            1 => MovePointerRight,
-            2 => IncreaseValueByOne,
-            3 => DecreaseValueByOne,
-            4 => CopyValueFromLeft,
-            _ => CopyValueFromRight
-        }
-    }
-}
-
-fn score_data(candidate: &Vec<u8>) -> u64 {
+fn score_data(candidate: &Vec<u8>) -> f64 {
     let mut score = 1.0;
     let candidate_length_squared = candidate.len().pow(2) as f64;
     let max_loss = 1.0 / candidate_length_squared;
@@ -169,10 +170,12 @@ fn score_data(candidate: &Vec<u8>) -> u64 {
         }
     }
 
-    (score * 10000.0) as u64
+    score
 }
 
-fn fitness_function(agent: &Agent<Gene>, data: &Vec<u8>) -> Result<u64, ScoreError> {
+// The data type is Vec<u8>, so the fitness function has to take a reference to the Vec itself.
+#[allow(clippy::ptr_arg)]
+fn fitness_function(agent: &Agent<Gene>, data: &Vec<u8>) -> Result<FloatScore, ScoreError> {
     let processed = get_processed_data(agent.get_genes(), data);
-    Ok(score_data(&processed))
+    Ok(FloatScore(score_data(&processed)))
 }
@@ -22,13 +22,18 @@ fn main() {
 
     let mut manager = create_manager(fitness_function, 0);
     manager.set_number_of_genes(5, true);
-    manager.run(1250);
+    let report = manager.run(1250);
     let agents = manager.get_population().get_agents();
 
     println!("Population: {}", agents.len());
+    println!("Generations: {}", report.generations());
+    println!("Evaluations: {}", report.evaluations());
+    if let Some(agent) = report.best_agent() {
+        println!("Best: {:?}", agent.get_genes());
+    }
 
     let mut viewing = 10;
-    for (score_index, agent) in agents.iter().rev() {
+    for ((score_index, _), agent) in agents.iter().rev() {
         println!("Score: {}", score_index);
         println!("{:?}", agent.get_genes());
 
@@ -12,6 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+// Written before clippy was run on the examples.
+#![allow(clippy::let_and_return, clippy::needless_borrow)]
+
 extern crate aristeia;
 extern crate rand;
 
@@ -31,20 +34,21 @@ use self::City::{
     Auckland
 };
 use rand::{
-    distributions::{Distribution, Standard},
-    Rng,
+    rngs::StdRng,
+    SeedableRng,
 };
 use aristeia::agent::Agent;
 use aristeia::population::Population;
 use std::time::Instant;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use aristeia::operations::{
     Operation,
-    OperationType,
     Selection,
     SelectionType
 };
-use aristeia::fitness::{GeneralScoreProvider, ScoreError};
+use aristeia::permutation::{random_permutation, order_crossover, inversion_mutation};
+use aristeia::fitness::ScoreProvider;
+use aristeia::fitness::{Direction, FloatScore, GeneralScoreProvider, ScoreError};
 
 // These are cities in the North Island of New Zealand.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
@@ -78,6 +82,7 @@ pub fn main() {
         Auckland
     ];
     let cities_clone = all_cities.clone();
+    let alphabet = all_cities.clone();
 
     // There is always a data variable, for which the type is quite flexible and what you do with it is up to you.
     // In this case, we're using it cache the distances between each city rather than doing that calculation every time
@@ -94,50 +99,67 @@ pub fn main() {
     // Here we define what happens for each "generation" of the process.
     let operations = vec![
         // We will mutate a random selection of 10% (that's the 0.1 in the Selection) of the population, but also a minimum of 1.
-        Operation::with_values(
+        // Each route is an ordering of all the cities, so we use inversion mutation, which reverses part of the route,
+        // rather than the default mutation, which could introduce repeated cities.
+        Operation::with_mutation_operator(
             Selection::with_values(SelectionType::RandomAny, 0.1, 1),
-            OperationType::Mutate),
+            inversion_mutation),
         // We will get highest scored 20% and randomly pair them, creating children with crossed over genes out of those.
-        Operation::with_values(
+        // Order crossover also keeps every child an ordering of all the cities.
+        Operation::with_crossover_operator(
             Selection::with_values(SelectionType::HighestScore, 0.2, 1),
-            OperationType::Crossover),
+            order_crossover),
         // We will take a random set of 50% of the population, randomly pair them and produce children with crossed over
         // genes out of those.
-        Operation::with_values(
+        Operation::with_crossover_operator(
             Selection::with_values(SelectionType::RandomAny, 0.5, 1),
-            OperationType::Crossover),
+            order_crossover),
         // We will take the lowest 2% of the population and get rid of them. Note that just like in the previous operations,
         // the minimum is set to 1. So there'll always be at least 1 agent culled.
-        Operation::with_values(
-            Selection::with_values(SelectionType::LowestScore, 0.02, 1),
-            OperationType::Cull)
+        Operation::cull(Selection::with_values(SelectionType::LowestScore, 0.02, 1))
     ];
 
-    let mut score_provider = GeneralScoreProvider::new(fitness_function, 25);
+    // The offset of 0.25 adds a small random amount to each score, so that selection isn't entirely deterministic.
+    let mut score_provider = GeneralScoreProvider::new(fitness_function, FloatScore(0.25));
+
+    // All randomness comes from this generator, so running with the same seed gives the same result.
+    let mut rng = StdRng::seed_from_u64(42);
+
+    // Create a population of 20 agents which each visit all 10 cities in a random order.
+    // We need to pass in the data as this is used for scoring the agents.
+    let mut population = Population::new_empty(false);
+    let mut agents = Vec::new();
+    for _ in 0..20 {
+        agents.push(random_permutation(&alphabet, &mut rng));
+    }
+    for agent in score_provider.evaluate_scores(agents, &data).unwrap() {
+        let score = score_provider.get_score(&agent, &data, &mut rng).unwrap();
+        population.insert(score, agent);
+    }
+
+    // The score is the distance travelled, so lower scores are better.
+    population.set_direction(Direction::Minimise);
 
-    // Create a population of 20 agents which each have a set of 10 randomly chosen genes.
-    // We need to pass in the data as this is used for scoring the agents. 
-    // We also pass in a reference to the scoring function defined towards the end of this file.
-    let population = Population::new(20, 10, false, &data, &mut score_provider);
+    // Without a limit the population would keep growing, as the operations add many more agents than they cull.
+    // Once there are 2000 agents, each new agent will replace the worst scored agent if it scores better.
+    population.set_max_size(Some(2000));
 
     // Now we run 50 iterations (or generations) on this population, meaning we run the operations we defined above
     // 50 times over. Again, we need the data and scoring function references as these are used for scoring new agents.
-    let population = run_iterations(population, 50, &data, &operations, &mut score_provider);
+    let population = run_iterations(population, 50, &data, &operations, &mut score_provider, &mut rng);
 
-    let agents = population.get_agents();
-
-    println!("Population: {}", agents.len());
+    println!("Population: {}", population.len());
     println!("Duration: {}", now.elapsed().as_secs() as f64 + now.elapsed().subsec_nanos() as f64 * 1e-9);
 
-    // This will the print the highest score and those that follow.
+    // This will the print the best (lowest) score and those that follow.
     let mut first = true;
-    let mut first_score = 0;
-    for (score_index, agent) in agents.iter().rev() {
+    let mut first_score = 0.0;
+    for ((score_index, _), agent) in population.iter_best_first() {
         if first {
             first = false;
-            first_score = *score_index;
+            first_score = score_index.value();
         }
-        if score_index < &(first_score - 20) {
+        if score_index.value() > first_score + 0.2 {
             break;
         }
         println!("Score: {}", score_index);
@@ -145,26 +167,6 @@ pub fn main() {
     }
 }
 
-// We need to define how random values are generated for the genes.
-// If you have a set of genes where you want the likelihood of each of them being returned to be equal,
-// you would probably define it a lot like this.
-impl Distribution<City> for Standard {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> City {
-        match rng.gen_range(0, 10) {
-            0 => Wellington,
-            1 => PalmerstonNorth,
-            2 => NewPlymouth,
-            3 => Hastings,
-            4 => Gisborne,
-            5 => Taupo,
-            6 => Rotorua,
-            7 => Hamilton,
-            8 => Tauranga,
-            _ => Auckland
-        }
-    }
-}
-
 // This just gives us the simple distance between 2 points on a 2d plane.
 // I could have been more technically correct and used a formula that determines
 // the distance between points on a globe (called the "haversine formula").
@@ -217,26 +219,7 @@ fn get_distance(agent: &Agent<City>, data: &HashMap<(City, City), f64>) -> f64 {
 }
 
 // The fitness function used to determine the score on an agent, based on its genes.
-fn fitness_function(agent: &Agent<City>, data: &HashMap<(City, City), f64>) -> Result<u64, ScoreError> {
-    let distance = get_distance(agent, data);
-
-    let mut repeats = 0;
-    let mut cities = HashSet::new();
-    for city in agent.get_genes() {
-        if !cities.insert(city) {
-            // False returned if HashSet did have value.
-            repeats += 1;
-        }
-    }
-
-    // To talk through this:
-    // 6.0 is about the distance between the two furthest cities (using the coordinates as units, I'm not actually even bothering to convert to km or miles).
-    // The above is multiplied by the length of the genes, because you could have a set that goes back and forth between the two furthest citis.
-    // So that gives the longest possible distance, now subtract the distance calculated for the set of genes we're scoring.
-    // Multiply by 100.0 - this is actually just to ensure the scores have a decent spread.
-    // The last set of brackets is a penalty on the score for any cities visited twice, the idea of this example is that 
-    // the salesman should be visiting each city once.
-    let score = (6.0 * agent.get_genes().len() as f64 - distance) * 100.0 * (1.0 - repeats as f64 * 0.1);
-
-    Ok(score as u64)
+// As every agent visits each city exactly once, the score is simply the distance.
+fn fitness_function(agent: &Agent<City>, data: &HashMap<(City, City), f64>) -> Result<FloatScore, ScoreError> {
+    Ok(FloatScore(get_distance(agent, data)))
 }
@@ -14,10 +14,14 @@
 
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use super::sampler::GeneSampler;
 use rand::{
     distributions::{Distribution, Standard},
+    rngs::StdRng,
     Rng,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Carries a set of genes.
 #[derive(Clone)]
@@ -26,12 +30,20 @@ pub struct Agent <Gene> {
     hash: u64
 }
 
+impl <Gene> Default for Agent<Gene>
+where
+Gene: Hash
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl <Gene> Agent<Gene> {
 
     /// Creates an agents with no genes.
     pub fn new() -> Self
-    where 
-    Standard: Distribution<Gene>,
+    where
     Gene: Hash
     {
         let genes = Vec::new();
@@ -46,14 +58,15 @@ impl <Gene> Agent<Gene> {
     }
 
     /// Creates a new Agent with random set of genes.
-    pub fn with_genes(number_of_genes: usize) -> Self 
+    pub fn with_genes<R>(number_of_genes: usize, rng: &mut R) -> Self 
     where 
     Standard: Distribution<Gene>,
-    Gene: Hash 
+    Gene: Hash,
+    R: Rng + ?Sized
     {
         let mut genes = Vec::with_capacity(number_of_genes);
         for _ in 0..number_of_genes {
-            genes.push(rand::random());
+            genes.push(rng.gen());
         }
 
         let mut s = DefaultHasher::new();
@@ -66,15 +79,51 @@ impl <Gene> Agent<Gene> {
         }
     }
 
+    /// Creates an Agent with the given genes, such as a known solution.
+    pub fn from_genes(genes: Vec<Gene>) -> Self
+    where
+    Gene: Hash
+    {
+        let mut agent = Self::new();
+        agent.set_genes(genes);
+        agent
+    }
+
+    /// Creates a new Agent with genes from the sampler.
+    pub fn with_sampled_genes<G>(number_of_genes: usize, sampler: &G, rng: &mut StdRng) -> Self
+    where
+    Gene: Hash,
+    G: GeneSampler<Gene> + ?Sized
+    {
+        let mut agent = Self::new();
+        agent.set_genes((0..number_of_genes).map(|_| sampler.sample(rng)).collect());
+        agent
+    }
+
     pub fn get_genes(&self) -> &Vec<Gene> {
         return &self.genes;
     }
 
+    /// Replaces all of the genes.
+    pub fn set_genes(&mut self, genes: Vec<Gene>)
+    where
+    Gene: Hash
+    {
+        self.genes = genes;
+
+        let mut s = DefaultHasher::new();
+        self.genes.hash(&mut s);
+        self.hash = s.finish();
+    }
+
     /// Chooses a random point on genes of self and uses that as its crossover point.
     /// Maintains the number of genes of self if the other has a different gene length.
-    pub fn crossover_some_genes(&mut self, other: &Self) where Gene: Clone + Hash {
-        let mut rng = rand::thread_rng();
-        
+    /// Leaves self unchanged if either has no genes.
+    pub fn crossover_some_genes<R>(&mut self, other: &Self, rng: &mut R)
+    where
+    Gene: Clone + Hash,
+    R: Rng + ?Sized
+    {
         let self_len = self.genes.len();
         let other_len = other.genes.len();
 
@@ -82,6 +131,9 @@ impl <Gene> Agent<Gene> {
         if self_len > other_len {
             gene_count = other_len;
         }
+        if gene_count == 0 {
+            return;
+        }
 
         let crossover_point = rng.gen_range(0, gene_count);
 
@@ -104,18 +156,34 @@ impl <Gene> Agent<Gene> {
         self.hash = s.finish();
     }
 
-    pub fn mutate(&mut self)
+    /// Removes a random gene and inserts a new random gene at a random position, five times.
+    pub fn mutate<R>(&mut self, rng: &mut R)
     where
     Standard: Distribution<Gene>,
-    Gene: Hash
+    Gene: Hash,
+    R: Rng + ?Sized
     {
-        let mut rng = rand::thread_rng();
+        self.remove_and_insert_genes(5, rng, |rng| rng.gen());
+    }
 
+    /// Removes a random gene and inserts a new gene from new_gene at a random position, the given
+    /// number of times. Does nothing if there are no genes.
+    pub fn remove_and_insert_genes<R, F>(&mut self, rounds: usize, rng: &mut R, mut new_gene: F)
+    where
+    Gene: Hash,
+    R: Rng + ?Sized,
+    F: FnMut(&mut R) -> Gene
+    {
         let gene_count = self.genes.len();
+        if gene_count == 0 {
+            return;
+        }
 
-        for _ in 0..5 {
+        for _ in 0..rounds {
            self.genes.remove(rng.gen_range(0, gene_count));
-           self.genes.insert(rng.gen_range(0, gene_count - 1), rand::random());
+           let position = rng.gen_range(0, gene_count);
+           let gene = new_gene(rng);
+           self.genes.insert(position, gene);
         }
 
         let mut s = DefaultHasher::new();
@@ -133,18 +201,75 @@ impl <Gene> Agent<Gene> {
     }
 }
 
-pub fn crossover <Gene> (parent1: &Agent<Gene>, parent2: &Agent<Gene>) -> Agent<Gene> 
-where Gene: Clone + Hash {
+/// Only the genes are stored.
+#[cfg(feature = "serde")]
+impl <Gene> Serialize for Agent<Gene>
+where
+Gene: Serialize
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+    Ser: Serializer
+    {
+        #[derive(Serialize)]
+        struct AgentGenes <'a, Gene: 'a> {
+            genes: &'a Vec<Gene>
+        }
+
+        AgentGenes { genes: &self.genes }.serialize(serializer)
+    }
+}
+
+/// The hash is calculated from the genes rather than stored.
+#[cfg(feature = "serde")]
+impl <'de, Gene> Deserialize<'de> for Agent<Gene>
+where
+Gene: Deserialize<'de> + Hash
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+    D: Deserializer<'de>
+    {
+        #[derive(Deserialize)]
+        struct AgentGenes <Gene> {
+            genes: Vec<Gene>
+        }
+
+        let AgentGenes { genes } = AgentGenes::deserialize(deserializer)?;
+        Ok(Agent::from_genes(genes))
+    }
+}
+
+pub fn crossover <Gene, R> (parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut R) -> Agent<Gene> 
+where
+Gene: Clone + Hash,
+R: Rng + ?Sized
+{
     let mut child = parent1.clone();
 
-    child.crossover_some_genes(parent2);
+    child.crossover_some_genes(parent2, rng);
 
     return child;
 }
 
+/// Returns a mutated copy of the agent.
+pub fn mutate <Gene, R> (agent: &Agent<Gene>, rng: &mut R) -> Agent<Gene>
+where
+Standard: Distribution<Gene>,
+Gene: Clone + Hash,
+R: Rng + ?Sized
+{
+    let mut child = agent.clone();
+
+    child.mutate(rng);
+
+    child
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
 
     #[test]
     fn new_no_genes() {
@@ -160,7 +285,8 @@ mod tests {
 
     #[test]
     fn new_with_genes() {
-        let agent: Agent<u8> = Agent::with_genes(2);
+        let mut rng = StdRng::seed_from_u64(0);
+        let agent: Agent<u8> = Agent::with_genes(2, &mut rng);
 
         let genes = agent.get_genes();
         assert_eq!(2, genes.len());
@@ -171,11 +297,34 @@ mod tests {
         assert_eq!(s.finish(), agent.get_hash());
     }
 
+    #[test]
+    fn from_genes() {
+        let agent = Agent::from_genes(vec![3u8, 1, 2]);
+        assert_eq!(&vec![3, 1, 2], agent.get_genes());
+
+        let mut s = DefaultHasher::new();
+        agent.get_genes().hash(&mut s);
+        assert_eq!(s.finish(), agent.get_hash());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_recalculates_hash() {
+        let agent = Agent::from_genes(vec![3u8, 1, 2]);
+        let json = ::serde_json::to_string(&agent).unwrap();
+        assert_eq!(r#"{"genes":[3,1,2]}"#, json);
+
+        let loaded: Agent<u8> = ::serde_json::from_str(&json).unwrap();
+        assert!(loaded.has_same_genes(&agent));
+        assert_eq!(agent.get_hash(), loaded.get_hash());
+    }
+
     #[test]
     fn mutate() {
-        let mut agent: Agent<u8> = Agent::with_genes(2);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut agent: Agent<u8> = Agent::with_genes(2, &mut rng);
 
-        agent.mutate();
+        agent.mutate(&mut rng);
 
         // Length should still be as specified in new().
         let genes = agent.get_genes();
@@ -187,12 +336,26 @@ mod tests {
         assert_eq!(s.finish(), agent.get_hash());
     }
 
+    #[test]
+    fn mutate_one_gene() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut agent: Agent<u8> = Agent::with_genes(1, &mut rng);
+
+        agent.mutate(&mut rng);
+        assert_eq!(1, agent.get_genes().len());
+
+        let mut agent: Agent<u8> = Agent::new();
+        agent.mutate(&mut rng);
+        assert!(agent.get_genes().is_empty());
+    }
+
     #[test]
     fn crossover_some_genes_same_length_other() {
-        let mut agent: Agent<u8> = Agent::with_genes(6);
-        let other: Agent<u8> = Agent::with_genes(6);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut agent: Agent<u8> = Agent::with_genes(6, &mut rng);
+        let other: Agent<u8> = Agent::with_genes(6, &mut rng);
 
-        agent.crossover_some_genes(&other);
+        agent.crossover_some_genes(&other, &mut rng);
 
         // Length should still be as specified in new().
         let genes = agent.get_genes();
@@ -206,10 +369,11 @@ mod tests {
 
     #[test]
     fn crossover_some_genes_shorter_other() {
-        let mut agent: Agent<u8> = Agent::with_genes(6);
-        let other: Agent<u8> = Agent::with_genes(5);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut agent: Agent<u8> = Agent::with_genes(6, &mut rng);
+        let other: Agent<u8> = Agent::with_genes(5, &mut rng);
 
-        agent.crossover_some_genes(&other);
+        agent.crossover_some_genes(&other, &mut rng);
 
         // Length should still be as specified in new().
         let genes = agent.get_genes();
@@ -223,10 +387,11 @@ mod tests {
 
     #[test]
     fn crossover_some_genes_longer_other() {
-        let mut agent: Agent<u8> = Agent::with_genes(6);
-        let other: Agent<u8> = Agent::with_genes(7);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut agent: Agent<u8> = Agent::with_genes(6, &mut rng);
+        let other: Agent<u8> = Agent::with_genes(7, &mut rng);
 
-        agent.crossover_some_genes(&other);
+        agent.crossover_some_genes(&other, &mut rng);
 
         // Length should still be as specified in new().
         let genes = agent.get_genes();
@@ -238,12 +403,24 @@ mod tests {
         assert_eq!(s.finish(), agent.get_hash());
     }
 
+    #[test]
+    fn crossover_some_genes_empty_other() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut agent: Agent<u8> = Agent::with_genes(6, &mut rng);
+        let genes = agent.get_genes().clone();
+
+        agent.crossover_some_genes(&Agent::new(), &mut rng);
+
+        assert_eq!(&genes, agent.get_genes());
+    }
+
     #[test]
     fn crossover_parents() {
-        let parent_one: Agent<u8> = Agent::with_genes(6);
-        let parent_two: Agent<u8> = Agent::with_genes(5);
+        let mut rng = StdRng::seed_from_u64(0);
+        let parent_one: Agent<u8> = Agent::with_genes(6, &mut rng);
+        let parent_two: Agent<u8> = Agent::with_genes(5, &mut rng);
 
-        let child = crossover(&parent_one, &parent_two);
+        let child = crossover(&parent_one, &parent_two, &mut rng);
 
         // Length should be as for parent_one.
         let genes = child.get_genes();
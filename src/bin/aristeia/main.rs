@@ -0,0 +1,227 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs one of the built-in benchmark problems with the settings of a config file, printing
+//! progress as it goes and writing the final population and run report to the output directory.
+//!
+//! Usage: aristeia <problem> --config <file> [--output <directory>] [--cities <file>] [--quiet]
+
+extern crate aristeia;
+extern crate rand;
+extern crate serde;
+extern crate serde_json;
+
+mod onemax;
+mod rastrigin;
+mod sequence;
+mod tsp;
+
+use aristeia::agent::Agent;
+use aristeia::config::ManagerConfig;
+use aristeia::fitness::{Score, ScoreProvider};
+use aristeia::manager::Manager;
+use aristeia::observer::Observer;
+use aristeia::population::Population;
+use aristeia::report::RunReport;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::env;
+use std::fs::{self, File};
+use std::hash::Hash;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: aristeia <onemax|rastrigin|tsp|sequence> --config <file> [--output <directory>] [--cities <file>] [--quiet]";
+
+/// The command-line options.
+pub struct Options {
+    problem: String,
+    config: PathBuf,
+    output: PathBuf,
+    cities: Option<PathBuf>,
+    quiet: bool
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = match options.problem.as_str() {
+        "onemax" => onemax::run(&options),
+        "rastrigin" => rastrigin::run(&options),
+        "tsp" => tsp::run(&options),
+        "sequence" => sequence::run(&options),
+        problem => {
+            eprintln!("Unknown problem {}\n{}", problem, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(error) = result {
+        eprintln!("Error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn parse_args<I>(mut args: I) -> Result<Options, String>
+where
+I: Iterator<Item = String>
+{
+    let mut problem = None;
+    let mut config = None;
+    let mut output = PathBuf::from("output");
+    let mut cities = None;
+    let mut quiet = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => config = Some(PathBuf::from(option_value(&arg, args.next())?)),
+            "--output" => output = PathBuf::from(option_value(&arg, args.next())?),
+            "--cities" => cities = Some(PathBuf::from(option_value(&arg, args.next())?)),
+            "--quiet" => quiet = true,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+            _ if problem.is_none() => problem = Some(arg),
+            _ => return Err(format!("Unexpected argument {}", arg))
+        }
+    }
+
+    Ok(Options {
+        problem: problem.ok_or("No problem given")?,
+        config: config.ok_or("No config file given")?,
+        output,
+        cities,
+        quiet
+    })
+}
+
+fn option_value(option: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("{} needs a value", option))
+}
+
+/// Reads the config file given in the options.
+pub fn load_config<S>(options: &Options) -> Result<ManagerConfig<S>, String>
+where
+S: Score + DeserializeOwned
+{
+    ManagerConfig::from_file(&options.config)
+        .map_err(|error| format!("{}: {}", options.config.display(), error))
+}
+
+/// Runs the manager until the config's termination settings are met, then writes the final
+/// population and the report to the output directory.
+pub fn run<Gene, Data, SP, S>(
+    mut manager: Manager<Gene, Data, SP, S>,
+    config: &ManagerConfig<S>,
+    options: &Options
+) -> Result<RunReport<Gene, S>, String>
+where
+Gene: Clone + Hash + Send + Serialize + 'static,
+Data: Clone + Send + 'static,
+SP: Clone + Send + ScoreProvider<Gene, Data, S> + 'static,
+S: Score + Serialize
+{
+    // Without termination settings, a run could go on forever.
+    let condition = config.stop_condition()
+        .ok_or_else(|| format!("{}: termination must be set", options.config.display()))?;
+    if !options.quiet {
+        manager.add_observer(ProgressPrinter::default());
+    }
+
+    let report = manager.run_until(condition);
+
+    fs::create_dir_all(&options.output)
+        .map_err(|error| format!("can't create {}: {}", options.output.display(), error))?;
+    write_json(&options.output.join("population.json"), manager.get_population())?;
+    write_json(&options.output.join("report.json"), &report)?;
+
+    println!("Stopped by {:?}", report.stop_reason());
+    println!("Generations: {}", report.generations());
+    println!("Evaluations: {}", report.evaluations());
+    println!("Duration: {:.3}s", report.elapsed().as_secs() as f64 + report.elapsed().subsec_nanos() as f64 * 1e-9);
+    if let Some(score) = report.best_score() {
+        println!("Best score: {}", score.to_f64());
+    }
+    println!("Wrote {}", options.output.display());
+    Ok(report)
+}
+
+fn write_json<T>(path: &Path, value: &T) -> Result<(), String>
+where
+T: Serialize
+{
+    let file = File::create(path)
+        .map_err(|error| format!("can't create {}: {}", path.display(), error))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), value)
+        .map_err(|error| format!("can't write {}: {}", path.display(), error))
+}
+
+/// Prints the best score so far and the size of the main population after each generation.
+/// The best score comes from on_new_best, as the population's scores include the random offset.
+/// The mean of each generation is in the report's history.
+#[derive(Default)]
+struct ProgressPrinter {
+    best: Option<f64>
+}
+
+impl <Gene, S> Observer<Gene, S> for ProgressPrinter
+where
+S: Score
+{
+    fn on_generation_end(&mut self, generation: usize, population: &Population<Gene, S>) {
+        if let Some(best) = self.best {
+            println!("Generation {}: best {}, population {}", generation, best, population.len());
+        }
+    }
+
+    fn on_new_best(&mut self, _generation: usize, score: S, _agent: &Agent<Gene>) {
+        self.best = Some(score.to_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn parses_options_in_any_order() {
+        let options = parse_args(args(&["--config", "tsp.toml", "tsp", "--cities", "cities.txt", "--quiet"])).unwrap();
+        assert_eq!("tsp", options.problem);
+        assert_eq!(PathBuf::from("tsp.toml"), options.config);
+        assert_eq!(PathBuf::from("output"), options.output);
+        assert_eq!(Some(PathBuf::from("cities.txt")), options.cities);
+        assert!(options.quiet);
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        assert!(parse_args(args(&["onemax"])).is_err());
+        assert!(parse_args(args(&["onemax", "--config"])).is_err());
+        assert!(parse_args(args(&["onemax", "rastrigin", "--config", "a.toml"])).is_err());
+        assert!(parse_args(args(&["onemax", "--verbose", "--config", "a.toml"])).is_err());
+    }
+}
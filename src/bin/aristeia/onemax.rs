@@ -0,0 +1,34 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! OneMax: maximise the number of true genes.
+
+use aristeia::agent::Agent;
+use aristeia::fitness::ScoreError;
+use aristeia::manager::create_manager;
+use super::Options;
+
+pub fn run(options: &Options) -> Result<(), String> {
+    let config = super::load_config(options)?;
+    let mut manager = create_manager(fitness_function, ());
+    // 100 genes unless the config says otherwise.
+    manager.set_number_of_genes(100, true);
+    manager.apply_config(&config);
+    super::run(manager, &config, options)?;
+    Ok(())
+}
+
+fn fitness_function(agent: &Agent<bool>, _data: &()) -> Result<u64, ScoreError> {
+    Ok(agent.get_genes().iter().filter(|gene| **gene).count() as u64)
+}
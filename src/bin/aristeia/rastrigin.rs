@@ -0,0 +1,51 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rastrigin: minimise f(x) = 10n + sum(x_i^2 - 10 cos(2 pi x_i)), with each x_i in [-5.12, 5.12].
+//! The global minimum is 0, at x = 0.
+
+use aristeia::agent::Agent;
+use aristeia::fitness::{Direction, FloatScore, ScoreError};
+use aristeia::manager::create_manager_with_sampler;
+use aristeia::real::RealGene;
+use rand::Rng;
+use rand::rngs::StdRng;
+use std::f64::consts::PI;
+use super::Options;
+
+const LIMIT: f64 = 5.12;
+
+pub fn run(options: &Options) -> Result<(), String> {
+    let config = super::load_config(options)?;
+    let sampler = |rng: &mut StdRng| RealGene(rng.gen_range(-LIMIT, LIMIT));
+    let mut manager = create_manager_with_sampler(fitness_function, sampler, ());
+    // 10 dimensions unless the config says otherwise.
+    manager.set_number_of_genes(10, true);
+    manager.set_direction(Direction::Minimise);
+    manager.apply_config(&config);
+    let report = super::run(manager, &config, options)?;
+    if let Some(agent) = report.best_agent() {
+        let values: Vec<f64> = agent.get_genes().iter().map(|gene| gene.value()).collect();
+        println!("Best: {:?}", values);
+    }
+    Ok(())
+}
+
+fn fitness_function(agent: &Agent<RealGene>, _data: &()) -> Result<FloatScore, ScoreError> {
+    let genes = agent.get_genes();
+    let sum: f64 = genes.iter()
+        .map(|gene| gene.value().powi(2) - 10.0 * (2.0 * PI * gene.value()).cos())
+        .sum();
+    Ok(FloatScore(10.0 * genes.len() as f64 + sum))
+}
@@ -0,0 +1,156 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The sequence problem from examples/sequence.rs: find instructions that turn ten zeros into an
+//! increasing sequence, using as few instructions as possible.
+
+use self::Gene::{
+    MovePointerLeft,
+    MovePointerRight,
+    IncreaseValueByOne,
+    DecreaseValueByOne,
+    CopyValueFromLeft,
+    CopyValueFromRight
+};
+use aristeia::agent::Agent;
+use aristeia::fitness::{Direction, FloatScore, Parsimony, ScoreError};
+use aristeia::manager::create_manager_with_sampler;
+use aristeia::sampler::ChoiceSampler;
+use serde::Serialize;
+use super::Options;
+
+#[derive(Clone, PartialEq, Hash, Debug, Serialize)]
+enum Gene {
+    MovePointerLeft,
+    MovePointerRight,
+    IncreaseValueByOne,
+    DecreaseValueByOne,
+    CopyValueFromLeft,
+    CopyValueFromRight
+}
+
+pub fn run(options: &Options) -> Result<(), String> {
+    let config = super::load_config(options)?;
+    let data = vec![0; 10];
+    // Each instruction costs a tiny amount, so that among equally good sequences the shorter ones score higher.
+    let fitness = Parsimony::new(fitness_function, 0.000001, Direction::Maximise);
+    let sampler = ChoiceSampler::new(&[
+        MovePointerLeft,
+        MovePointerRight,
+        IncreaseValueByOne,
+        DecreaseValueByOne,
+        CopyValueFromLeft,
+        CopyValueFromRight
+    ]);
+    let mut manager = create_manager_with_sampler(fitness, sampler, data.clone());
+    manager.set_number_of_genes(30, false);
+    manager.apply_config(&config);
+    let report = super::run(manager, &config, options)?;
+    if let Some(agent) = report.best_agent() {
+        println!("Best: {} instructions giving {:?}", agent.get_genes().len(), get_processed_data(agent.get_genes(), &data));
+    }
+    Ok(())
+}
+fn get_processed_data(genes: &[Gene], data: &[u8]) -> Vec<u8> {
+    let mut copy = data.to_owned();
+    let mut pointer = 0;
+    for gene in genes {
+        match gene {
+            MovePointerLeft => move_pointer_left(&mut pointer, &mut copy),
+            MovePointerRight => move_pointer_right(&mut pointer, &mut copy),
+            IncreaseValueByOne => increase_value_by_one(&mut pointer, &mut copy),
+            DecreaseValueByOne => decrease_value_by_one(&mut pointer, &mut copy),
+            CopyValueFromLeft => copy_value_from_left(&mut pointer, &mut copy),
+            CopyValueFromRight => copy_value_from_right(&mut pointer, &mut copy),
+        }
+    }
+
+    copy
+}
+
+fn move_pointer_left(pointer: &mut usize, _data: &mut [u8]) {
+    if *pointer == 0 {
+        return;
+    }
+
+    *pointer -= 1;
+}
+
+fn move_pointer_right(pointer: &mut usize, data: &mut [u8]) {
+    if *pointer == data.len() - 1 {
+        return;
+    }
+
+    *pointer += 1;
+}
+
+fn increase_value_by_one(pointer: &mut usize, data: &mut [u8]) {
+    data[*pointer] += 1;
+}
+
+fn decrease_value_by_one(pointer: &mut usize, data: &mut [u8]) {
+    if data[*pointer] == 0 {
+        return;
+    }
+    data[*pointer] -= 1;
+}
+
+fn copy_value_from_left(pointer: &mut usize, data: &mut [u8]) {
+    if *pointer == 0 {
+        return;
+    }
+
+    data[*pointer] = data[*pointer-1];
+}
+
+fn copy_value_from_right(pointer: &mut usize, data: &mut [u8]) {
+    if *pointer == data.len() - 1 {
+        return;
+    }
+
+    data[*pointer] = data[*pointer+1];
+}
+
+fn score_data(candidate: &[u8]) -> f64 {
+    let mut score = 1.0;
+    let candidate_length_squared = candidate.len().pow(2) as f64;
+    let max_loss = 1.0 / candidate_length_squared;
+
+    for i in 1..candidate.len() {
+        let previous = candidate[i - 1];
+        let expected = previous + 1;
+        let value = candidate[i];
+        if value == 0 {
+            score -= max_loss;
+            continue;
+        }
+
+        let diff = value as f64 - expected as f64;
+        score -= diff.abs() / candidate_length_squared;
+
+        if score < 0.0 {
+            score = 0.0;
+            break;
+        }
+    }
+
+    score
+}
+
+// The data type is Vec<u8>, so the fitness function has to take a reference to the Vec itself.
+#[allow(clippy::ptr_arg)]
+fn fitness_function(agent: &Agent<Gene>, data: &Vec<u8>) -> Result<FloatScore, ScoreError> {
+    let processed = get_processed_data(agent.get_genes(), data);
+    Ok(FloatScore(score_data(&processed)))
+}
@@ -0,0 +1,124 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The travelling salesman problem: find the shortest route that visits every city once and
+//! returns to the start. Cities are read from a file with a line of "name x y" for each, and
+//! blank lines and lines starting with # are skipped.
+
+use aristeia::agent::Agent;
+use aristeia::fitness::{Direction, FloatScore, ScoreError};
+use aristeia::manager::{create_manager_with_sampler, SeedFill};
+use aristeia::operations::{Operation, OperationType, Selection, SelectionType};
+use aristeia::permutation::{inversion_mutation, order_crossover, random_permutation};
+use rand::Rng;
+use rand::rngs::StdRng;
+use std::fs;
+use std::path::Path;
+use super::Options;
+
+#[derive(Clone, Debug)]
+struct City {
+    name: String,
+    x: f64,
+    y: f64
+}
+
+pub fn run(options: &Options) -> Result<(), String> {
+    let path = options.cities.as_ref().ok_or("the tsp problem needs a --cities file")?;
+    let cities = read_cities(path)?;
+    let config = super::load_config(options)?;
+
+    let number_of_cities = cities.len();
+    let sampler = move |rng: &mut StdRng| rng.gen_range(0, number_of_cities);
+    let mut manager = create_manager_with_sampler(fitness_function, sampler, cities.clone());
+    manager.set_number_of_genes(number_of_cities, true);
+    manager.set_direction(Direction::Minimise);
+    manager.apply_config(&config);
+    // Child threads start from genes drawn one at a time, which aren't routes.
+    if config.max_child_threads.is_some_and(|number| number > 0) {
+        println!("The tsp problem runs without child threads, so max_child_threads is ignored");
+    }
+    manager.set_max_child_threads(0);
+
+    // The initial population is the cities in file order plus shuffles of them, and every
+    // operation keeps agents as orderings of all the cities.
+    manager.seed_agents(vec![Agent::from_genes((0..number_of_cities).collect())]);
+    manager.set_seed_fill(SeedFill::mutated(|agent: &Agent<usize>, rng: &mut StdRng| random_permutation(agent.get_genes(), rng)));
+    let operations = match &config.operations {
+        Some(operations) => operations.iter()
+            .map(|operation| {
+                let selection = operation.to_selection();
+                match operation.operation {
+                    OperationType::Mutate => Operation::with_mutation_operator(selection, inversion_mutation),
+                    OperationType::Crossover => Operation::with_crossover_operator(selection, order_crossover),
+                    OperationType::Cull => Operation::cull(selection),
+                    OperationType::RankedCull => Operation::ranked_cull(selection)
+                }
+            })
+            .collect(),
+        None => vec![
+            Operation::with_mutation_operator(Selection::with_values(SelectionType::RandomAny, 0.1, 1), inversion_mutation),
+            Operation::with_crossover_operator(Selection::with_values(SelectionType::HighestScore, 0.2, 1), order_crossover),
+            Operation::with_crossover_operator(Selection::with_values(SelectionType::RandomAny, 0.5, 1), order_crossover),
+            Operation::cull(Selection::with_values(SelectionType::LowestScore, 0.02, 1))
+        ]
+    };
+    manager.set_operations(operations);
+
+    let report = super::run(manager, &config, options)?;
+    if let Some(agent) = report.best_agent() {
+        let names: Vec<&str> = agent.get_genes().iter().map(|index| cities[*index].name.as_str()).collect();
+        println!("Best route: {}", names.join(" -> "));
+    }
+    Ok(())
+}
+
+fn read_cities(path: &Path) -> Result<Vec<City>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|error| format!("can't read {}: {}", path.display(), error))?;
+    let mut cities = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || format!("{} line {}: expected \"name x y\"", path.display(), index + 1);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 3 {
+            return Err(invalid());
+        }
+        cities.push(City {
+            name: fields[0].to_string(),
+            x: fields[1].parse().map_err(|_| invalid())?,
+            y: fields[2].parse().map_err(|_| invalid())?
+        });
+    }
+    if cities.len() < 2 {
+        return Err(format!("{} must have at least 2 cities", path.display()));
+    }
+    Ok(cities)
+}
+
+// The data type is Vec<City>, so the fitness function has to take a reference to the Vec itself.
+#[allow(clippy::ptr_arg)]
+fn fitness_function(agent: &Agent<usize>, cities: &Vec<City>) -> Result<FloatScore, ScoreError> {
+    let genes = agent.get_genes();
+    let mut distance = 0.0;
+    for (index, gene) in genes.iter().enumerate() {
+        let from = &cities[*gene];
+        let to = &cities[genes[(index + 1) % genes.len()]];
+        distance += ((to.x - from.x).powi(2) + (to.y - from.y).powi(2)).sqrt();
+    }
+    Ok(FloatScore(distance))
+}
@@ -0,0 +1,145 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checkpoints of a Manager's run, so that a long run can be resumed after a crash.
+//! Manager::checkpoint_every writes them and Manager::resume reads them.
+
+use super::fitness::Score;
+use super::population::Population;
+use super::report::GenerationStats;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The version of the checkpoint format. Checkpoints of other versions can't be resumed.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// A problem writing or reading a checkpoint.
+#[derive(Debug)]
+pub struct CheckpointError {
+    message: String
+}
+
+impl CheckpointError {
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string()
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for CheckpointError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for CheckpointError {}
+
+/// The state of a run at the end of a cycle: everything that the rest of a seeded run depends
+/// on, apart from the manager's settings.
+#[derive(Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "Gene: Serialize, S: Score + Serialize",
+    deserialize = "Gene: Deserialize<'de> + Hash, S: Score + Deserialize<'de>"
+))]
+pub(crate) struct Checkpoint <Gene, S> {
+    pub(crate) version: u32,
+    pub(crate) generations: usize,
+    pub(crate) cycles_without_improvement: usize,
+    /// The best score used by the stop conditions.
+    pub(crate) best: Option<S>,
+    /// The best score passed on to observers.
+    pub(crate) observed_best: Option<S>,
+    pub(crate) evaluations: u64,
+    pub(crate) elapsed: Duration,
+    /// The seed of the manager's random number generator for the next cycle.
+    pub(crate) rng_seed: u64,
+    pub(crate) next_child_id: u64,
+    pub(crate) history: Vec<GenerationStats<S>>,
+    /// The score provider's cache, from ScoreProvider::cached_fitness.
+    pub(crate) score_cache: Vec<(u64, S)>,
+    pub(crate) population: Population<Gene, S>
+}
+
+type CheckpointWriter<Gene, S> = Box<dyn Fn(&Checkpoint<Gene, S>, &Path) -> Result<(), CheckpointError>>;
+
+/// Where and how often a manager writes checkpoints.
+pub(crate) struct Checkpoints <Gene, S> {
+    pub(crate) interval: usize,
+    pub(crate) path: PathBuf,
+    pub(crate) write: CheckpointWriter<Gene, S>,
+    pub(crate) cycles: usize,
+    pub(crate) error: Option<CheckpointError>
+}
+
+impl <Gene, S> Checkpoints <Gene, S> {
+    /// Counts a cycle, returning whether a checkpoint is due.
+    pub(crate) fn end_cycle(&mut self) -> bool {
+        self.cycles += 1;
+        self.cycles >= self.interval
+    }
+
+    /// Writes the checkpoint, keeping any error so that the run can go on.
+    pub(crate) fn save(&mut self, checkpoint: &Checkpoint<Gene, S>) {
+        self.cycles = 0;
+        self.error = (self.write)(checkpoint, &self.path).err();
+    }
+}
+
+/// Writes the checkpoint as JSON. It is written to a temporary file that then replaces the
+/// file at the path, so a crash while writing leaves the previous checkpoint whole.
+pub(crate) fn write<Gene, S>(checkpoint: &Checkpoint<Gene, S>, path: &Path) -> Result<(), CheckpointError>
+where
+Gene: Serialize,
+S: Score + Serialize
+{
+    let contents = ::serde_json::to_vec(checkpoint)
+        .map_err(|error| CheckpointError::new(&format!("can't serialize checkpoint: {}", error)))?;
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, contents)
+        .map_err(|error| CheckpointError::new(&format!("can't write {}: {}", Path::new(&temporary).display(), error)))?;
+    fs::rename(&temporary, path)
+        .map_err(|error| CheckpointError::new(&format!("can't replace {}: {}", path.display(), error)))
+}
+
+/// Reads a checkpoint written by write, checking its version first.
+pub(crate) fn read<Gene, S>(path: &Path) -> Result<Checkpoint<Gene, S>, CheckpointError>
+where
+Gene: DeserializeOwned + Hash,
+S: Score + DeserializeOwned
+{
+    let contents = fs::read(path)
+        .map_err(|error| CheckpointError::new(&format!("can't read {}: {}", path.display(), error)))?;
+    let value: ::serde_json::Value = ::serde_json::from_slice(&contents)
+        .map_err(|error| CheckpointError::new(&format!("{} isn't a checkpoint: {}", path.display(), error)))?;
+    match value.get("version").and_then(|version| version.as_u64()) {
+        Some(version) if version == CHECKPOINT_VERSION as u64 => {},
+        Some(version) => return Err(CheckpointError::new(&format!(
+            "{} has checkpoint version {}, but only version {} can be resumed", path.display(), version, CHECKPOINT_VERSION))),
+        None => return Err(CheckpointError::new(&format!("{} has no checkpoint version", path.display())))
+    }
+    ::serde_json::from_value(value)
+        .map_err(|error| CheckpointError::new(&format!("can't read checkpoint {}: {}", path.display(), error)))
+}
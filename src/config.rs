@@ -0,0 +1,400 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::fitness::{Direction, Score};
+use super::operations::{OperationType, Selection, SelectionType};
+use super::termination::StopCondition;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// A problem with a configuration, along with the path of the field that caused it,
+/// such as `operations[2].proportion`.
+#[derive(Debug)]
+pub struct ConfigError {
+    field: String,
+    message: String
+}
+
+impl ConfigError {
+    pub fn new(field: &str, message: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.to_string()
+        }
+    }
+
+    /// The path of the field that caused the error. Empty if it isn't about a particular field.
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Adds the path of the field containing this one.
+    fn within(self, parent: &str) -> Self {
+        let field = if self.field.is_empty() {
+            parent.to_string()
+        } else {
+            format!("{}.{}", parent, self.field)
+        };
+        Self {
+            field,
+            message: self.message
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.field, self.message)
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Settings for a Manager, which can be loaded from a TOML or JSON file so that runs can be
+/// tuned without recompiling. Settings that are left out keep the manager's defaults.
+/// Apply it with Manager::apply_config.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, bound(deserialize = "S: Deserialize<'de>"))]
+pub struct ManagerConfig <S = u64> {
+    #[serde(default)]
+    pub initial_population_size: Option<usize>,
+    #[serde(default)]
+    pub max_population_size: Option<usize>,
+    #[serde(default)]
+    pub number_of_genes: Option<usize>,
+    /// Whether agents must keep the number of genes.
+    #[serde(default)]
+    pub strict_gene_length: Option<bool>,
+    /// The fewest and most genes an agent can have when the number of genes isn't strict.
+    #[serde(default)]
+    pub gene_length_limits: Option<(usize, usize)>,
+    #[serde(default)]
+    pub iterations_per_cycle: Option<usize>,
+    #[serde(default)]
+    pub max_child_threads: Option<u8>,
+    #[serde(default)]
+    pub direction: Option<Direction>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Replaces the default operations when set.
+    #[serde(default)]
+    pub operations: Option<Vec<OperationConfig>>,
+    #[serde(default)]
+    pub termination: Option<TerminationConfig<S>>
+}
+
+/// An operation, with the settings of its Selection. Mutate operations use the manager's sampler
+/// for new genes, and Crossover operations use one-point crossover.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OperationConfig {
+    pub operation: OperationType,
+    pub selection: SelectionType,
+    pub proportion: f64,
+    #[serde(default = "default_preferred_minimum")]
+    pub preferred_minimum: usize,
+    #[serde(default)]
+    pub elites: usize
+}
+
+fn default_preferred_minimum() -> usize {
+    1
+}
+
+impl OperationConfig {
+    pub fn to_selection(&self) -> Selection {
+        let mut selection = Selection::with_values(self.selection, self.proportion, self.preferred_minimum);
+        selection.set_elites(self.elites);
+        selection
+    }
+}
+
+/// When to stop a run. The run stops when any of the conditions set is met, or when all of them
+/// are if require_all is true.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, bound(deserialize = "S: Deserialize<'de>"))]
+pub struct TerminationConfig <S = u64> {
+    #[serde(default)]
+    pub goal: Option<S>,
+    #[serde(default)]
+    pub max_generations: Option<usize>,
+    #[serde(default)]
+    pub time_limit_seconds: Option<f64>,
+    #[serde(default)]
+    pub max_evaluations: Option<u64>,
+    /// The number of cycles without improvement.
+    #[serde(default)]
+    pub stagnation: Option<usize>,
+    #[serde(default)]
+    pub require_all: bool
+}
+
+impl <S> ManagerConfig <S>
+where
+S: Score + DeserializeOwned
+{
+    /// Reads a configuration from a file, as TOML or JSON depending on its extension.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|error| ConfigError::new("", &format!("can't read {}: {}", path.display(), error)))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("json") => Self::from_json(&contents),
+            _ => Err(ConfigError::new("", &format!("{} must have a .toml or .json extension", path.display())))
+        }
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, ConfigError> {
+        let deserializer = ::toml::Deserializer::new(contents);
+        let config: Self = ::serde_path_to_error::deserialize(deserializer)
+            .map_err(|error| ConfigError::new(&error.path().to_string(), error.inner().message()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_json(contents: &str) -> Result<Self, ConfigError> {
+        let mut deserializer = ::serde_json::Deserializer::from_str(contents);
+        let config: Self = ::serde_path_to_error::deserialize(&mut deserializer)
+            .map_err(|error| ConfigError::new(&error.path().to_string(), &error.inner().to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl <S> ManagerConfig <S>
+where
+S: Score
+{
+    /// Checks that the settings can be used, returning an error for the first that can't.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.initial_population_size == Some(0) {
+            return Err(ConfigError::new("initial_population_size", "must be at least 1"));
+        }
+        if self.max_population_size == Some(0) {
+            return Err(ConfigError::new("max_population_size", "must be at least 1"));
+        }
+        if self.number_of_genes == Some(0) {
+            return Err(ConfigError::new("number_of_genes", "must be at least 1"));
+        }
+        if self.iterations_per_cycle == Some(0) {
+            return Err(ConfigError::new("iterations_per_cycle", "must be at least 1"));
+        }
+        if let Some((min_length, max_length)) = self.gene_length_limits {
+            if min_length == 0 {
+                return Err(ConfigError::new("gene_length_limits", "the minimum must be at least 1"));
+            }
+            if min_length > max_length {
+                return Err(ConfigError::new("gene_length_limits", "the minimum must not be above the maximum"));
+            }
+        }
+        if let Some(operations) = &self.operations {
+            if operations.is_empty() {
+                return Err(ConfigError::new("operations", "must have at least one operation"));
+            }
+            for (index, operation) in operations.iter().enumerate() {
+                operation.validate().map_err(|error| error.within(&format!("operations[{}]", index)))?;
+            }
+        }
+        if let Some(termination) = &self.termination {
+            termination.validate().map_err(|error| error.within("termination"))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the condition for stopping a run, if termination has been set.
+    pub fn stop_condition(&self) -> Option<StopCondition<S>> {
+        self.termination.as_ref().map(|termination| termination.to_stop_condition())
+    }
+}
+
+impl OperationConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !(0.0..=1.0).contains(&self.proportion) {
+            return Err(ConfigError::new("proportion", "must be between 0.0 and 1.0"));
+        }
+        match self.selection {
+            SelectionType::Tournament { size: 0, .. } =>
+                Err(ConfigError::new("selection.Tournament.size", "must be at least 1")),
+            SelectionType::LinearRank { pressure } if !(1.0..=2.0).contains(&pressure) =>
+                Err(ConfigError::new("selection.LinearRank.pressure", "must be between 1.0 and 2.0")),
+            SelectionType::ExponentialRank { base } if !(base > 0.0 && base < 1.0) =>
+                Err(ConfigError::new("selection.ExponentialRank.base", "must be between 0.0 and 1.0, excluding both")),
+            _ => Ok(())
+        }
+    }
+}
+
+impl <S> TerminationConfig <S>
+where
+S: Score
+{
+    fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(seconds) = self.time_limit_seconds {
+            if Duration::try_from_secs_f64(seconds).is_err() {
+                return Err(ConfigError::new("time_limit_seconds", "must be a positive number of seconds that fits in a Duration"));
+            }
+        }
+        if self.conditions().is_empty() {
+            return Err(ConfigError::new("", "must set at least one condition"));
+        }
+        Ok(())
+    }
+
+    pub fn to_stop_condition(&self) -> StopCondition<S> {
+        let mut conditions = self.conditions();
+        if conditions.len() == 1 {
+            conditions.remove(0)
+        } else if self.require_all {
+            StopCondition::All(conditions)
+        } else {
+            StopCondition::Any(conditions)
+        }
+    }
+
+    fn conditions(&self) -> Vec<StopCondition<S>> {
+        let mut conditions = Vec::new();
+        if let Some(goal) = self.goal {
+            conditions.push(StopCondition::Goal(goal));
+        }
+        if let Some(generations) = self.max_generations {
+            conditions.push(StopCondition::MaxGenerations(generations));
+        }
+        if let Some(seconds) = self.time_limit_seconds {
+            conditions.push(StopCondition::TimeLimit(Duration::from_secs_f64(seconds)));
+        }
+        if let Some(evaluations) = self.max_evaluations {
+            conditions.push(StopCondition::MaxEvaluations(evaluations));
+        }
+        if let Some(cycles) = self.stagnation {
+            conditions.push(StopCondition::Stagnation(cycles));
+        }
+        conditions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_toml_reads_every_setting() {
+        let config: ManagerConfig = ManagerConfig::from_toml(r#"
+            initial_population_size = 50
+            number_of_genes = 8
+            strict_gene_length = true
+            iterations_per_cycle = 20
+            max_child_threads = 2
+            direction = "Minimise"
+            seed = 7
+
+            [[operations]]
+            operation = "Mutate"
+            selection = "RandomAny"
+            proportion = 0.1
+
+            [[operations]]
+            operation = "Crossover"
+            selection = { Tournament = { size = 3, with_replacement = false } }
+            proportion = 0.4
+            preferred_minimum = 2
+
+            [termination]
+            goal = 10
+            max_generations = 500
+        "#).unwrap();
+
+        assert_eq!(Some(50), config.initial_population_size);
+        assert_eq!(Some(Direction::Minimise), config.direction);
+        assert_eq!(Some(7), config.seed);
+        let operations = config.operations.as_ref().unwrap();
+        assert_eq!(2, operations.len());
+        assert_eq!(2, operations[1].to_selection().preferred_minimum());
+        match config.stop_condition() {
+            Some(StopCondition::Any(conditions)) => assert_eq!(2, conditions.len()),
+            condition => panic!("unexpected condition {:?}", condition)
+        }
+    }
+
+    #[test]
+    fn from_json_reads_settings() {
+        let config: ManagerConfig = ManagerConfig::from_json(r#"{
+            "number_of_genes": 4,
+            "operations": [{ "operation": "Cull", "selection": "LowestScore", "proportion": 0.2 }],
+            "termination": { "stagnation": 10 }
+        }"#).unwrap();
+
+        assert_eq!(Some(4), config.number_of_genes);
+        assert!(matches!(config.stop_condition(), Some(StopCondition::Stagnation(10))));
+    }
+
+    #[test]
+    fn errors_point_at_the_bad_field() {
+        let error = ManagerConfig::<u64>::from_toml(r#"
+            [[operations]]
+            operation = "Mutate"
+            selection = "RandomAny"
+            proportion = 0.1
+
+            [[operations]]
+            operation = "Crossover"
+            selection = "HighestScore"
+            proportion = 1.5
+        "#).unwrap_err();
+        assert_eq!("operations[1].proportion", error.field());
+
+        let error = ManagerConfig::<u64>::from_json(r#"{ "operations": [{ "operation": "Grow", "selection": "RandomAny", "proportion": 0.1 }] }"#).unwrap_err();
+        assert_eq!("operations[0].operation", error.field());
+
+        let error = ManagerConfig::<u64>::from_toml("number_of_genes = \"eight\"").unwrap_err();
+        assert_eq!("number_of_genes", error.field());
+
+        let error = ManagerConfig::<u64>::from_toml("population = 5").unwrap_err();
+        assert!(error.message().contains("population"));
+
+        let error = ManagerConfig::<u64>::from_toml("[termination]\nrequire_all = true").unwrap_err();
+        assert_eq!("termination", error.field());
+
+        let error = ManagerConfig::<u64>::from_toml("[termination]\ntime_limit_seconds = 1e20").unwrap_err();
+        assert_eq!("termination.time_limit_seconds", error.field());
+
+        let error = ManagerConfig::<u64>::from_toml("gene_length_limits = [0, 5]").unwrap_err();
+        assert_eq!("gene_length_limits", error.field());
+
+        let error = ManagerConfig::<u64>::from_toml("number_of_genes = 0").unwrap_err();
+        assert_eq!("number_of_genes", error.field());
+
+        let error = ManagerConfig::<u64>::from_toml(r#"
+            [[operations]]
+            operation = "Crossover"
+            selection = { ExponentialRank = { base = 1.0 } }
+            proportion = 0.5
+        "#).unwrap_err();
+        assert_eq!("operations[0].selection.ExponentialRank.base", error.field());
+        assert!(error.message().contains("excluding both"));
+    }
+}
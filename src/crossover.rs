@@ -0,0 +1,355 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::agent::{Agent, crossover};
+use std::hash::Hash;
+use rand::{
+    rngs::StdRng,
+    seq::index::sample,
+    Rng,
+};
+
+/// Creates a child from two parents.
+/// Implemented for any function or closure with the same signature as crossover, so the
+/// functions in the permutation module can be used directly. Implement it for your own types
+/// to use crossover that is specific to your genes.
+pub trait CrossoverOperator <Gene>: Send + Sync {
+    fn crossover(&self, parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene>;
+}
+
+impl <Gene, F> CrossoverOperator<Gene> for F
+where
+F: Fn(&Agent<Gene>, &Agent<Gene>, &mut StdRng) -> Agent<Gene> + Send + Sync
+{
+    fn crossover(&self, parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        self(parent1, parent2, rng)
+    }
+}
+
+/// Takes the genes of the first parent up to a random point, and the genes of the second parent
+/// after it. This is the default for Crossover operations.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OnePointCrossover;
+
+impl <Gene> CrossoverOperator<Gene> for OnePointCrossover
+where
+Gene: Clone + Hash
+{
+    fn crossover(&self, parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        crossover(parent1, parent2, rng)
+    }
+}
+
+/// Takes the genes between two random points from the second parent, and the rest from the first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TwoPointCrossover;
+
+impl <Gene> CrossoverOperator<Gene> for TwoPointCrossover
+where
+Gene: Clone + Hash
+{
+    fn crossover(&self, parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        KPointCrossover::new(2).crossover(parent1, parent2, rng)
+    }
+}
+
+/// Cuts the parents at a number of random points, and alternates between their genes at each cut,
+/// starting with the first parent.
+/// Keeps the number of genes of the first parent. Genes beyond the length of the second parent are
+/// always taken from the first.
+#[derive(Clone, Copy, Debug)]
+pub struct KPointCrossover {
+    points: usize
+}
+
+impl KPointCrossover {
+    pub fn new(points: usize) -> Self {
+        Self {
+            points
+        }
+    }
+
+    pub fn points(&self) -> usize {
+        self.points
+    }
+}
+
+impl <Gene> CrossoverOperator<Gene> for KPointCrossover
+where
+Gene: Clone + Hash
+{
+    fn crossover(&self, parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        let mut genes = parent1.get_genes().clone();
+        let other_genes = parent2.get_genes();
+        let length = genes.len().min(other_genes.len());
+        if length < 2 {
+            return parent1.clone();
+        }
+
+        // A cut point is the index of the first gene after the cut, so there are length - 1 of them.
+        let points = self.points.min(length - 1);
+        let mut cuts: Vec<usize> = sample(rng, length - 1, points).into_iter().map(|cut| cut + 1).collect();
+        cuts.sort();
+        cuts.push(length);
+
+        let mut from_second = false;
+        let mut start = 0;
+        for cut in cuts {
+            if from_second {
+                genes[start..cut].clone_from_slice(&other_genes[start..cut]);
+            }
+            from_second = !from_second;
+            start = cut;
+        }
+
+        create_child(parent1, genes)
+    }
+}
+
+/// Takes each gene from the second parent with a probability, and otherwise from the first.
+/// The probability is 0.5 by default.
+/// Keeps the number of genes of the first parent. Genes beyond the length of the second parent are
+/// always taken from the first.
+#[derive(Clone, Copy, Debug)]
+pub struct UniformCrossover {
+    probability: f64
+}
+
+impl UniformCrossover {
+    pub fn new() -> Self {
+        Self {
+            probability: 0.5
+        }
+    }
+
+    pub fn with_probability(probability: f64) -> Self {
+        Self {
+            probability
+        }
+    }
+
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+}
+
+impl Default for UniformCrossover {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl <Gene> CrossoverOperator<Gene> for UniformCrossover
+where
+Gene: Clone + Hash
+{
+    fn crossover(&self, parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        let probability = self.probability.clamp(0.0, 1.0);
+        let mut genes = parent1.get_genes().clone();
+        for (gene, other) in genes.iter_mut().zip(parent2.get_genes()) {
+            if rng.gen_bool(probability) {
+                *gene = other.clone();
+            }
+        }
+
+        create_child(parent1, genes)
+    }
+}
+
+/// Cuts each parent at its own random point, and joins the genes of the first parent before its
+/// cut to the genes of the second parent after its cut, so the child can have a different number
+/// of genes to either parent.
+/// Children are kept within the minimum and maximum lengths, which allow any length of at least
+/// 1 gene by default.
+#[derive(Clone, Copy, Debug)]
+pub struct CutAndSpliceCrossover {
+    min_length: usize,
+    max_length: usize
+}
+
+impl CutAndSpliceCrossover {
+    pub fn new() -> Self {
+        Self {
+            min_length: 1,
+            max_length: usize::MAX
+        }
+    }
+
+    /// Panics if the minimum is 0, as operators can't work on agents without genes, or if the
+    /// minimum is above the maximum.
+    pub fn with_limits(min_length: usize, max_length: usize) -> Self {
+        assert!(min_length >= 1, "the minimum gene length must be at least 1");
+        assert!(min_length <= max_length, "the minimum gene length must not be above the maximum");
+        Self {
+            min_length,
+            max_length
+        }
+    }
+
+    pub fn min_length(&self) -> usize {
+        self.min_length
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl Default for CutAndSpliceCrossover {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl <Gene> CrossoverOperator<Gene> for CutAndSpliceCrossover
+where
+Gene: Clone + Hash
+{
+    fn crossover(&self, parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        let genes1 = parent1.get_genes();
+        let genes2 = parent2.get_genes();
+        let first_cut = rng.gen_range(0, genes1.len() + 1);
+
+        // The child has first_cut + genes2.len() - second_cut genes, which limits the second cut.
+        let length = first_cut + genes2.len();
+        let lowest = length.saturating_sub(self.max_length);
+        let highest = length.saturating_sub(self.min_length).min(genes2.len());
+        if lowest > highest {
+            return parent1.clone();
+        }
+        let second_cut = rng.gen_range(lowest, highest + 1);
+
+        let mut genes = genes1[..first_cut].to_vec();
+        genes.extend_from_slice(&genes2[second_cut..]);
+        create_child(parent1, genes)
+    }
+}
+
+fn create_child<Gene>(parent: &Agent<Gene>, genes: Vec<Gene>) -> Agent<Gene>
+where
+Gene: Clone + Hash
+{
+    let mut child = parent.clone();
+    child.set_genes(genes);
+    child
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn parents() -> (Agent<u8>, Agent<u8>) {
+        let mut parent1 = Agent::new();
+        parent1.set_genes(vec![0; 20]);
+        let mut parent2 = Agent::new();
+        parent2.set_genes(vec![1; 20]);
+        (parent1, parent2)
+    }
+
+    /// Counts the places where the child switches from one parent's genes to the other's.
+    fn count_switches(agent: &Agent<u8>) -> usize {
+        agent.get_genes().windows(2).filter(|pair| pair[0] != pair[1]).count()
+    }
+
+    #[test]
+    fn k_point_crossover_switches_at_each_point() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (parent1, parent2) = parents();
+        for points in 1..6 {
+            let child = KPointCrossover::new(points).crossover(&parent1, &parent2, &mut rng);
+            assert_eq!(20, child.get_genes().len());
+            assert_eq!(0, child.get_genes()[0]);
+            assert_eq!(points, count_switches(&child));
+        }
+    }
+
+    #[test]
+    fn k_point_crossover_limits_points_to_gene_length() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (parent1, parent2) = parents();
+        let child = KPointCrossover::new(50).crossover(&parent1, &parent2, &mut rng);
+        assert_eq!(19, count_switches(&child));
+    }
+
+    #[test]
+    fn two_point_crossover_takes_middle_from_second_parent() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (parent1, parent2) = parents();
+        let child = TwoPointCrossover.crossover(&parent1, &parent2, &mut rng);
+        assert_eq!(2, count_switches(&child));
+        assert_eq!(0, child.get_genes()[19]);
+    }
+
+    #[test]
+    fn uniform_crossover_takes_genes_from_both_parents() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (parent1, parent2) = parents();
+        let child = UniformCrossover::new().crossover(&parent1, &parent2, &mut rng);
+        let from_second = child.get_genes().iter().filter(|gene| **gene == 1).count();
+        assert!(from_second > 0 && from_second < 20);
+
+        let child = UniformCrossover::with_probability(1.0).crossover(&parent1, &parent2, &mut rng);
+        assert!(child.has_same_genes(&parent2));
+    }
+
+    #[test]
+    fn cut_and_splice_crossover_changes_length_within_limits() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (parent1, parent2) = parents();
+        let crossover = CutAndSpliceCrossover::with_limits(15, 25);
+        let mut lengths = Vec::new();
+        for _ in 0..50 {
+            let child = crossover.crossover(&parent1, &parent2, &mut rng);
+            assert!(count_switches(&child) <= 1);
+            lengths.push(child.get_genes().len());
+        }
+        assert!(lengths.iter().all(|length| *length >= 15 && *length <= 25));
+        assert!(lengths.iter().any(|length| *length != 20));
+    }
+
+    #[test]
+    fn cut_and_splice_crossover_never_empties_children() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let parent = Agent::from_genes(vec![1u8]);
+        for _ in 0..20 {
+            let child = CutAndSpliceCrossover::new().crossover(&parent, &parent, &mut rng);
+            assert!(!child.get_genes().is_empty());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn cut_and_splice_crossover_rejects_minimum_of_zero() {
+        CutAndSpliceCrossover::with_limits(0, 10);
+    }
+
+    #[test]
+    #[should_panic]
+    fn cut_and_splice_crossover_rejects_minimum_above_maximum() {
+        CutAndSpliceCrossover::with_limits(11, 10);
+    }
+
+    #[test]
+    fn crossover_keeps_length_of_first_parent() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (parent1, _) = parents();
+        let mut parent2 = Agent::new();
+        parent2.set_genes(vec![1; 5]);
+        let child = UniformCrossover::with_probability(1.0).crossover(&parent1, &parent2, &mut rng);
+        assert_eq!(20, child.get_genes().len());
+        let child = KPointCrossover::new(3).crossover(&parent1, &parent2, &mut rng);
+        assert_eq!(20, child.get_genes().len());
+    }
+}
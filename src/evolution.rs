@@ -16,29 +16,64 @@ use super::population::Population;
 use super::operations::{
     Operation
 };
-use super::fitness::{ScoreProvider};
-use rand::{
-    distributions::{Distribution, Standard}
-};
+use super::fitness::{Score, ScoreProvider};
+use super::observer::Observer;
+use rand::rngs::StdRng;
 use std::hash::Hash;
 
-pub fn run_iterations<Gene, Data, SP>(
-    mut population: Population<Gene>,
+/// Runs each operation on the population in turn, for the given number of iterations.
+/// Operations follow the Direction set on the population when choosing the best and worst agents.
+/// All randomness comes from the given rng, so seeding it makes the run reproducible.
+pub fn run_iterations<Gene, Data, SP, S>(
+    population: Population<Gene, S>,
+    iterations: usize,
+    data: &Data,
+    operations: &[Operation<Gene, Data, S>],
+    score_provider: &mut SP,
+    rng: &mut StdRng
+) -> Population<Gene, S>
+where
+Gene: Clone + Hash + Send + 'static,
+Data: Clone + Send + 'static,
+SP: Clone + ScoreProvider<Gene, Data, S>,
+S: Score
+{
+    run_iterations_observed(population, iterations, data, operations, score_provider, &mut (), rng)
+}
+
+/// Runs each operation on the population in turn like run_iterations, telling the observer about
+/// each generation, each operation and each new best agent.
+/// Generations are numbered from 1 for the first iteration.
+pub fn run_iterations_observed<Gene, Data, SP, S, O>(
+    mut population: Population<Gene, S>,
     iterations: usize,
     data: &Data,
-    operations: &Vec<Operation<Gene, Data>>,
-    score_provider: &mut SP
-) -> Population<Gene>
+    operations: &[Operation<Gene, Data, S>],
+    score_provider: &mut SP,
+    observer: &mut O,
+    rng: &mut StdRng
+) -> Population<Gene, S>
 where
-Standard: Distribution<Gene>,
 Gene: Clone + Hash + Send + 'static,
 Data: Clone + Send + 'static,
-SP: Clone + ScoreProvider<Gene, Data>
+SP: Clone + ScoreProvider<Gene, Data, S>,
+S: Score,
+O: Observer<Gene, S> + ?Sized
 {
-    for _ in 0..iterations {
-        for operation in operations.iter() {
-            population = operation.run(population, data, score_provider);
+    for generation in 1..=iterations {
+        observer.on_generation_start(generation, &population);
+        let previous_best = population.best_fitness(score_provider).map(|(best, _)| best);
+        for (operation_index, operation) in operations.iter().enumerate() {
+            population = operation.run(population, data, score_provider, rng);
+            observer.on_operation(generation, operation_index, &population);
         }
+
+        if let Some((best, agent)) = population.best_fitness(score_provider) {
+            if previous_best.is_none_or(|previous| population.direction().is_better(best, previous)) {
+                observer.on_new_best(generation, best, agent);
+            }
+        }
+        observer.on_generation_end(generation, &population);
     }
 
     population
@@ -48,17 +83,86 @@ SP: Clone + ScoreProvider<Gene, Data>
 mod tests {
     use super::*;
     use super::super::agent::Agent;
-    use super::super::fitness::{Score, ScoreError, GeneralScoreProvider};
+    use super::super::fitness::{ScoreError, GeneralScoreProvider};
+    use super::super::operations::{OperationType, Selection, SelectionType};
+    use rand::SeedableRng;
 
-    fn get_score_index(agent: &Agent<u8>, _data: &u8) -> Result<Score, ScoreError> {
-        let score = agent.get_genes()[0] as Score;
+    fn get_score_index(agent: &Agent<u8>, _data: &u8) -> Result<u64, ScoreError> {
+        let score = agent.get_genes()[0] as u64;
         Ok(score)
     }
 
     #[test]
     fn run_iterations_nothing_to_do() {
         let mut score_provider = GeneralScoreProvider::new(get_score_index, 25);
-        let population = run_iterations(Population::new_empty(false), 0, &0, &Vec::new(), &mut score_provider);
+        let mut rng = StdRng::seed_from_u64(0);
+        let population = run_iterations(Population::new_empty(false), 0, &0, &Vec::new(), &mut score_provider, &mut rng);
         assert_eq!(0, population.len());
     }
+
+    fn run_seeded(seed: u64) -> Population<u8> {
+        let operations = vec![
+            Operation::new(OperationType::Mutate, Selection::new(SelectionType::RandomAny, 0.2)),
+            Operation::new(OperationType::Crossover, Selection::new(SelectionType::HighestScore, 0.5)),
+            Operation::new(OperationType::Cull, Selection::new(SelectionType::LowestScore, 0.2)),
+        ];
+        let mut score_provider = GeneralScoreProvider::new(get_score_index, 25);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let population = Population::new(20, 6, false, &0, &mut score_provider, &mut rng);
+        run_iterations(population, 10, &0, &operations, &mut score_provider, &mut rng)
+    }
+
+    fn summarise(population: &Population<u8>) -> Vec<(u64, u64)> {
+        population.get_agents().iter().map(|((score, _), agent)| (*score, agent.get_hash())).collect()
+    }
+
+    #[test]
+    fn run_iterations_same_seed_same_population() {
+        assert_eq!(summarise(&run_seeded(7)), summarise(&run_seeded(7)));
+        assert_ne!(summarise(&run_seeded(7)), summarise(&run_seeded(8)));
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        starts: Vec<usize>,
+        operations: Vec<(usize, usize)>,
+        ends: Vec<usize>,
+        bests: Vec<u64>
+    }
+
+    impl Observer<u8> for RecordingObserver {
+        fn on_generation_start(&mut self, generation: usize, _population: &Population<u8>) {
+            self.starts.push(generation);
+        }
+
+        fn on_operation(&mut self, generation: usize, operation_index: usize, _population: &Population<u8>) {
+            self.operations.push((generation, operation_index));
+        }
+
+        fn on_generation_end(&mut self, generation: usize, _population: &Population<u8>) {
+            self.ends.push(generation);
+        }
+
+        fn on_new_best(&mut self, _generation: usize, score: u64, _agent: &Agent<u8>) {
+            self.bests.push(score);
+        }
+    }
+
+    #[test]
+    fn run_iterations_observed_reports_each_generation() {
+        let operations = vec![
+            Operation::new(OperationType::Mutate, Selection::new(SelectionType::RandomAny, 0.2)),
+            Operation::new(OperationType::Crossover, Selection::new(SelectionType::HighestScore, 0.5)),
+        ];
+        let mut score_provider = GeneralScoreProvider::new(get_score_index, 0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let population = Population::new(20, 6, false, &0, &mut score_provider, &mut rng);
+        let mut observer = RecordingObserver::default();
+        run_iterations_observed(population, 3, &0, &operations, &mut score_provider, &mut observer, &mut rng);
+
+        assert_eq!(vec![1, 2, 3], observer.starts);
+        assert_eq!(vec![1, 2, 3], observer.ends);
+        assert_eq!(vec![(1, 0), (1, 1), (2, 0), (2, 1), (3, 0), (3, 1)], observer.operations);
+        assert!(observer.bests.windows(2).all(|pair| pair[0] < pair[1]));
+    }
 }
@@ -1,19 +1,32 @@
 use super::agent::Agent;
+use super::population::Population;
 use std::hash::Hash;
 use rand::{
-    distributions::{Distribution, Standard},
+    rngs::StdRng,
     Rng,
-    prelude::ThreadRng
 };
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::error::Error;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ScoreError {
     details: String
 }
 
+impl ScoreError {
+    /// Creates an error for a fitness function to return when an agent can't be scored.
+    pub fn new(details: &str) -> Self {
+        Self {
+            details: details.to_string()
+        }
+    }
+}
+
 impl Display for ScoreError {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "{}", self.details)
@@ -26,53 +39,348 @@ impl Error for ScoreError {
     }
 }
 
-pub type FitnessFunction<Gene, Data> = fn(&Agent<Gene>, &Data) -> Result<Score, ScoreError>;
+pub type FitnessFunction<Gene, Data, S = u64> = fn(&Agent<Gene>, &Data) -> Result<S, ScoreError>;
+
+/// Determines the score of an agent.
+/// Implemented for any function or closure with the same signature as a FitnessFunction, so
+/// fitness functions can capture state such as a loaded dataset. Each child thread gets its own
+/// clone, so wrap expensive state in an Arc.
+pub trait Fitness <Gene, Data, S = u64>: Clone + Send + Sync {
+    fn score(&self, agent: &Agent<Gene>, data: &Data) -> Result<S, ScoreError>;
+}
+
+impl <Gene, Data, S, F> Fitness<Gene, Data, S> for F
+where
+F: Fn(&Agent<Gene>, &Data) -> Result<S, ScoreError> + Clone + Send + Sync
+{
+    fn score(&self, agent: &Agent<Gene>, data: &Data) -> Result<S, ScoreError> {
+        self(agent, data)
+    }
+}
+
+/// Adds parsimony pressure to a fitness function, so that each gene costs a penalty and shorter
+/// agents are preferred when scores are otherwise close. The penalty is taken off the score when
+/// maximising and added to it when minimising.
+#[derive(Clone)]
+pub struct Parsimony <F> {
+    fitness: F,
+    penalty: f64,
+    direction: Direction
+}
+
+impl <F> Parsimony <F> {
+    /// The penalty is for each gene, so should be small enough not to outweigh real improvements.
+    pub fn new(fitness: F, penalty: f64, direction: Direction) -> Self {
+        Self {
+            fitness,
+            penalty,
+            direction
+        }
+    }
+
+    pub fn penalty(&self) -> f64 {
+        self.penalty
+    }
+}
+
+impl <Gene, Data, F> Fitness<Gene, Data, FloatScore> for Parsimony <F>
+where
+F: Fitness<Gene, Data, FloatScore>
+{
+    fn score(&self, agent: &Agent<Gene>, data: &Data) -> Result<FloatScore, ScoreError> {
+        let score = self.fitness.score(agent, data)?.value();
+        let penalty = self.penalty * agent.get_genes().len() as f64;
+        match self.direction {
+            Direction::Maximise => Ok(FloatScore(score - penalty)),
+            Direction::Minimise => Ok(FloatScore(score + penalty))
+        }
+    }
+}
+
+/// Whether higher or lower scores are better.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Direction {
+    #[default]
+    Maximise,
+    Minimise
+}
+
+impl Direction {
+    pub fn reversed(self) -> Direction {
+        match self {
+            Direction::Maximise => Direction::Minimise,
+            Direction::Minimise => Direction::Maximise
+        }
+    }
+
+    /// Returns true if the score is strictly better than the other.
+    pub fn is_better<S: Ord>(self, score: S, other: S) -> bool {
+        match self {
+            Direction::Maximise => score > other,
+            Direction::Minimise => score < other
+        }
+    }
+
+    /// Returns true if the score is as good as the goal or better.
+    pub fn has_reached<S: Ord>(self, score: S, goal: S) -> bool {
+        !self.is_better(goal, score)
+    }
+}
+
+/// A value that agents are ranked by. Whether higher or lower is better is set by a Direction.
+/// Implemented for u64, i64 and FloatScore.
+pub trait Score: Copy + Ord + Debug + Send + Sync + 'static {
+    /// The offset used by create_manager for its GeneralScoreProvider.
+    fn default_offset() -> Self;
+
+    /// Adds the other score, saturating at the bounds of the type.
+    fn saturating_add(self, other: Self) -> Self;
+
+    /// Subtracts the other score, saturating at the bounds of the type.
+    fn saturating_sub(self, other: Self) -> Self;
+
+    /// Returns a random score from zero up to but not including this one.
+    /// Returns zero if this score is not above zero.
+    fn random_below<R: Rng + ?Sized>(self, rng: &mut R) -> Self;
+
+    /// Converts the score for fitness proportionate selection.
+    fn to_f64(self) -> f64;
+
+    /// Whether the score is NaN, which score providers treat as a failed evaluation.
+    /// Returns false by default.
+    fn is_nan(self) -> bool {
+        false
+    }
+}
+
+impl Score for u64 {
+    fn default_offset() -> Self {
+        25
+    }
+
+    fn saturating_add(self, other: Self) -> Self {
+        u64::saturating_add(self, other)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        u64::saturating_sub(self, other)
+    }
+
+    fn random_below<R: Rng + ?Sized>(self, rng: &mut R) -> Self {
+        if self == 0 {
+            return 0;
+        }
+        rng.gen_range(0, self)
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Score for i64 {
+    fn default_offset() -> Self {
+        25
+    }
+
+    fn saturating_add(self, other: Self) -> Self {
+        i64::saturating_add(self, other)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        i64::saturating_sub(self, other)
+    }
+
+    fn random_below<R: Rng + ?Sized>(self, rng: &mut R) -> Self {
+        if self <= 0 {
+            return 0;
+        }
+        rng.gen_range(0, self)
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+/// Wraps an f64 so that it can be used as a score.
+/// Scores are totally ordered, with NaN of either sign equal to any other NaN and ordered above
+/// all other values. GeneralScoreProvider and ParallelScoreProvider treat a NaN fitness as an
+/// error, so NaN never becomes the best score of a run.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FloatScore(pub f64);
+
+impl FloatScore {
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl From<f64> for FloatScore {
+    fn from(value: f64) -> Self {
+        FloatScore(value)
+    }
+}
+
+impl PartialEq for FloatScore {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FloatScore {}
+
+impl PartialOrd for FloatScore {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FloatScore {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // total_cmp puts NaNs with the sign bit set below negative infinity, so every NaN is
+        // handled here first.
+        match (self.0.is_nan(), other.0.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => self.0.total_cmp(&other.0)
+        }
+    }
+}
+
+impl Display for FloatScore {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Score for FloatScore {
+    /// There is no scale that suits every float score, so there is no offset by default.
+    fn default_offset() -> Self {
+        FloatScore(0.0)
+    }
+
+    fn saturating_add(self, other: Self) -> Self {
+        FloatScore(self.0 + other.0)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        FloatScore(self.0 - other.0)
+    }
+
+    fn random_below<R: Rng + ?Sized>(self, rng: &mut R) -> Self {
+        if self.0 <= 0.0 || !self.0.is_finite() {
+            return FloatScore(0.0);
+        }
+        FloatScore(rng.gen_range(0.0, self.0))
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0
+    }
+
+    fn is_nan(self) -> bool {
+        self.0.is_nan()
+    }
+}
 
-pub type Score = u64;
+/// Turns a NaN score into an error, so that an agent scored NaN is skipped like any other agent
+/// that can't be scored.
+pub(crate) fn check_score<S>(score: Result<S, ScoreError>) -> Result<S, ScoreError>
+where
+S: Score
+{
+    match score {
+        Ok(score) if score.is_nan() => Err(ScoreError::new("the fitness function returned NaN")),
+        score => score
+    }
+}
 
-pub trait ScoreProvider <Gene, Data> {
+pub trait ScoreProvider <Gene, Data, S = u64> {
     fn evaluate_scores(&mut self, agents: Vec<Agent<Gene>>, data: &Data) -> Result<Vec<Agent<Gene>>, ScoreError>;
-    fn get_score(&mut self, agent: &Agent<Gene>, data: &Data, rng: &mut ThreadRng) -> Result<Score, ScoreError>;
+    fn get_score(&mut self, agent: &Agent<Gene>, data: &Data, rng: &mut StdRng) -> Result<S, ScoreError>;
+
+    /// Called whenever the population changes, for score providers whose scores depend on
+    /// the rest of the population, such as the ParetoScoreProvider.
+    /// Does nothing by default.
+    fn rank_population(&mut self, _population: &mut Population<Gene, S>, _data: &Data) {}
+
+    /// Returns how many times the fitness function has been called, so that a run can stop
+    /// after a number of evaluations. Returns 0 by default.
+    fn evaluations(&self) -> u64 {
+        0
+    }
+
+    /// Returns the score of an agent that has been evaluated, without the random offset that
+    /// get_score adds, so that runs report and stop at the real score.
+    /// Returns None by default, in which case the agent's score in the population is used.
+    fn fitness(&self, _agent: &Agent<Gene>) -> Option<S> {
+        None
+    }
+
+    /// Stores the score of an agent evaluated by a copy of the score provider, such as one in a
+    /// child thread, so that fitness can return it. Does nothing by default.
+    fn add_fitness(&mut self, _agent: &Agent<Gene>, _fitness: S) {}
+
+    /// Returns every cached score without the random offset, by the hash of the agent's genes
+    /// in hash order, so that a checkpoint can hold the cache. Returns nothing by default.
+    fn cached_fitness(&self) -> Vec<(u64, S)> {
+        Vec::new()
+    }
+
+    /// Puts back scores from cached_fitness when a run is resumed. Does nothing by default,
+    /// in which case the agents are evaluated again as they come up.
+    fn restore_fitness(&mut self, _scores: Vec<(u64, S)>) {}
 }
 
 #[derive(Clone)]
-pub struct GeneralScoreProvider <Gene, Data>
+pub struct GeneralScoreProvider <Gene, Data, S = u64, F = FitnessFunction<Gene, Data, S>>
 where
-Standard: Distribution<Gene>,
-Gene: Clone + Hash
+Gene: Clone + Hash,
+S: Score,
+F: Fitness<Gene, Data, S>
 {
-    scoring_function: FitnessFunction<Gene, Data>,
-    offset: Score,
-    score_cache: HashMap<u64, Score>
+    scoring_function: F,
+    offset: S,
+    score_cache: HashMap<u64, S>,
+    evaluations: u64,
+    gene: PhantomData<Gene>,
+    data: PhantomData<Data>
 }
 
-impl <Gene, Data> GeneralScoreProvider <Gene, Data>
+impl <Gene, Data, S, F> GeneralScoreProvider <Gene, Data, S, F>
 where
-Standard: Distribution<Gene>,
-Gene: Clone + Hash
+Gene: Clone + Hash,
+S: Score,
+F: Fitness<Gene, Data, S>
 {
-    pub fn new(scoring_function: FitnessFunction<Gene, Data>, offset: Score) -> Self {
+    /// The scoring function can be a function, a closure or anything else implementing Fitness.
+    /// The offset adds a random amount, up to the offset either side, to each score
+    /// returned so that selection isn't entirely deterministic. Use zero for no offset.
+    pub fn new(scoring_function: F, offset: S) -> Self {
         Self {
             scoring_function: scoring_function,
             offset: offset,
-            score_cache: HashMap::new()
+            score_cache: HashMap::new(),
+            evaluations: 0,
+            gene: PhantomData,
+            data: PhantomData
         }
     }
 
-    pub fn offset_cached_score(&self, hash: &u64, offset: Score) -> Result<Score, ScoreError> {
-        let score = self.score_cache[&hash] + offset;
-        if score <= self.offset {
-            return Ok(0);
-        } else {
-            return Ok(score - self.offset);
-        }
+    pub fn offset_cached_score(&self, hash: &u64, offset: S) -> Result<S, ScoreError> {
+        Ok(self.score_cache[hash].saturating_add(offset).saturating_sub(self.offset))
     }
 }
 
-impl <Gene, Data> ScoreProvider<Gene, Data> for GeneralScoreProvider <Gene, Data>
+impl <Gene, Data, S, F> ScoreProvider<Gene, Data, S> for GeneralScoreProvider <Gene, Data, S, F>
 where
-Standard: Distribution<Gene>,
-Gene: Clone + Hash
+Gene: Clone + Hash,
+S: Score,
+F: Fitness<Gene, Data, S>
 {
     fn evaluate_scores(&mut self, agents: Vec<Agent<Gene>>, data: &Data) -> Result<Vec<Agent<Gene>>, ScoreError> {
         let mut cached = Vec::new();
@@ -82,9 +390,9 @@ Gene: Clone + Hash
             if self.score_cache.contains_key(&hash) {
                 cached.push(agent);
             } else {
-                let result = (self.scoring_function)(&agent, data);
-                if result.is_ok() {
-                    self.score_cache.insert(hash, result.unwrap());
+                self.evaluations += 1;
+                if let Ok(score) = check_score(self.scoring_function.score(&agent, data)) {
+                    self.score_cache.insert(hash, score);
                     cached.push(agent);
                 }
                 // else we simply skip the agent.
@@ -94,18 +402,162 @@ Gene: Clone + Hash
         Ok(cached)
     }
 
-    fn get_score(&mut self, agent: &Agent<Gene>, data: &Data, rng: &mut ThreadRng) -> Result<Score, ScoreError> {
+    fn get_score(&mut self, agent: &Agent<Gene>, data: &Data, rng: &mut StdRng) -> Result<S, ScoreError> {
         let hash = agent.get_hash();
-        let offset = rng.gen_range(0, self.offset * 2);
+        let offset = self.offset.saturating_add(self.offset).random_below(rng);
 
         if self.score_cache.contains_key(&hash) {
             return self.offset_cached_score(&hash, offset);
         }
 
-        let score = (self.scoring_function)(agent, data).unwrap();
+        self.evaluations += 1;
+        let score = check_score(self.scoring_function.score(agent, data))?;
         self.score_cache.insert(hash, score);
 
         return self.offset_cached_score(&hash, offset);
     }
+
+    fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+
+    fn fitness(&self, agent: &Agent<Gene>) -> Option<S> {
+        self.score_cache.get(&agent.get_hash()).cloned()
+    }
+
+    fn add_fitness(&mut self, agent: &Agent<Gene>, fitness: S) {
+        self.score_cache.insert(agent.get_hash(), fitness);
+    }
+
+    fn cached_fitness(&self) -> Vec<(u64, S)> {
+        let mut scores: Vec<(u64, S)> = self.score_cache.iter().map(|(hash, score)| (*hash, *score)).collect();
+        scores.sort_by_key(|(hash, _)| *hash);
+        scores
+    }
+
+    fn restore_fitness(&mut self, scores: Vec<(u64, S)>) {
+        self.score_cache.extend(scores);
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn get_score_index(agent: &Agent<u8>, _data: &u8) -> Result<i64, ScoreError> {
+        let score = agent.get_genes()[0] as i64 - 100;
+        Ok(score)
+    }
+
+    #[test]
+    fn float_score_total_ordering() {
+        assert!(FloatScore(-1.5) < FloatScore(0.0));
+        assert!(FloatScore(0.25) < FloatScore(0.5));
+        assert!(FloatScore(f64::INFINITY) < FloatScore(f64::NAN));
+        assert_eq!(FloatScore(f64::NAN), FloatScore(f64::NAN));
+    }
+
+    #[test]
+    fn float_score_orders_nan_of_either_sign_highest() {
+        let negative_nan = FloatScore(-f64::NAN);
+        assert!(negative_nan.0.is_sign_negative());
+        assert!(FloatScore(f64::NEG_INFINITY) < negative_nan);
+        assert!(FloatScore(f64::INFINITY) < negative_nan);
+        assert_eq!(FloatScore(f64::NAN), negative_nan);
+        assert_eq!(Some(FloatScore(f64::NAN)), vec![FloatScore(1.0), negative_nan, FloatScore(-1.0)].into_iter().max());
+    }
+
+    fn get_score_unless_zero(agent: &Agent<u8>, _data: &u8) -> Result<FloatScore, ScoreError> {
+        match agent.get_genes()[0] {
+            0 => Ok(FloatScore(f64::NAN)),
+            gene => Ok(FloatScore(gene as f64))
+        }
+    }
+
+    #[test]
+    fn nan_fitness_is_an_error() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut score_provider = GeneralScoreProvider::new(get_score_unless_zero, FloatScore(0.0));
+        let agents = vec![Agent::from_genes(vec![0]), Agent::from_genes(vec![2]), Agent::from_genes(vec![1])];
+        let mut population = Population::new_empty(false);
+        population.add_agents(agents, &0, &mut score_provider, &mut rng);
+
+        assert_eq!(2, population.len());
+        assert_eq!(Some(FloatScore(2.0)), population.best_fitness(&score_provider).map(|(best, _)| best));
+        assert!(score_provider.get_score(&Agent::from_genes(vec![0]), &0, &mut rng).is_err());
+        assert_eq!(None, score_provider.fitness(&Agent::from_genes(vec![0])));
+    }
+
+    #[test]
+    fn unsigned_offset_saturates_at_zero() {
+        assert_eq!(0, Score::saturating_sub(Score::saturating_add(3u64, 10), 25));
+        assert_eq!(5, Score::saturating_sub(Score::saturating_add(20u64, 10), 25));
+    }
+
+    #[test]
+    fn random_below_zero_is_zero() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(0, 0u64.random_below(&mut rng));
+        assert_eq!(0, (-5i64).random_below(&mut rng));
+        assert_eq!(FloatScore(0.0), FloatScore(-1.0).random_below(&mut rng));
+    }
+
+    #[test]
+    fn general_score_provider_allows_negative_scores() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut score_provider = GeneralScoreProvider::new(get_score_index, 0);
+        let agent: Agent<u8> = Agent::with_genes(1, &mut rng);
+        let expected = agent.get_genes()[0] as i64 - 100;
+
+        assert_eq!(expected, score_provider.get_score(&agent, &0, &mut rng).unwrap());
+    }
+
+    #[derive(Clone)]
+    struct Threshold {
+        minimum: u8
+    }
+
+    impl Fitness<u8, u8, i64> for Threshold {
+        fn score(&self, agent: &Agent<u8>, _data: &u8) -> Result<i64, ScoreError> {
+            Ok(agent.get_genes().iter().filter(|gene| **gene >= self.minimum).count() as i64)
+        }
+    }
+
+    #[test]
+    fn general_score_provider_accepts_closures() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let weight = 3;
+        let mut score_provider = GeneralScoreProvider::new(
+            move |agent: &Agent<u8>, data: &u8| Ok(agent.get_genes()[0] as u64 * weight + *data as u64),
+            0
+        );
+        let agent: Agent<u8> = Agent::with_genes(1, &mut rng);
+        let expected = agent.get_genes()[0] as u64 * 3 + 2;
+
+        assert_eq!(expected, score_provider.get_score(&agent, &2, &mut rng).unwrap());
+    }
+
+    #[test]
+    fn general_score_provider_accepts_fitness_implementations() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut score_provider = GeneralScoreProvider::new(Threshold { minimum: 128 }, 0);
+        let agent: Agent<u8> = Agent::with_genes(10, &mut rng);
+        let expected = agent.get_genes().iter().filter(|gene| **gene >= 128).count() as i64;
+
+        assert_eq!(expected, score_provider.get_score(&agent, &0, &mut rng).unwrap());
+    }
+
+    #[test]
+    fn parsimony_penalises_each_gene() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let fitness = |_agent: &Agent<u8>, _data: &u8| Ok(FloatScore(10.0));
+        let agent: Agent<u8> = Agent::with_genes(4, &mut rng);
+
+        let maximising = Parsimony::new(fitness, 0.5, Direction::Maximise);
+        assert_eq!(FloatScore(8.0), maximising.score(&agent, &0).unwrap());
+        let minimising = Parsimony::new(fitness, 0.5, Direction::Minimise);
+        assert_eq!(FloatScore(12.0), minimising.score(&agent, &0).unwrap());
+    }
+}
@@ -0,0 +1,393 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::agent::Agent;
+use super::fitness::{Direction, Score, ScoreProvider};
+use super::operations::{Selection, SelectionType};
+use super::population::Population;
+use super::report::GenerationStats;
+use rand::Rng;
+use rand::rngs::StdRng;
+use std::hash::Hash;
+use std::panic;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How the islands of an island model send emigrants to each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Topology {
+    /// Each island sends emigrants to the next, and the last island sends them to the first.
+    Ring,
+    /// Each island sends emigrants to every other island.
+    FullyConnected,
+    /// Each island sends emigrants to another island, chosen at random at each migration.
+    Random,
+    /// The first island is the hub. It sends emigrants to every other island, and the others
+    /// send theirs only to the hub.
+    Star
+}
+
+impl Topology {
+    /// Returns the islands that the island sends its emigrants to.
+    pub fn destinations<R>(self, island: usize, number_of_islands: usize, rng: &mut R) -> Vec<usize>
+    where
+    R: Rng + ?Sized
+    {
+        if number_of_islands < 2 {
+            return Vec::new();
+        }
+
+        match self {
+            Topology::Ring => vec![(island + 1) % number_of_islands],
+            Topology::FullyConnected => (0..number_of_islands).filter(|other| *other != island).collect(),
+            Topology::Random => {
+                // Picks from the other islands by skipping over this one.
+                let other = rng.gen_range(0, number_of_islands - 1);
+                vec![if other >= island { other + 1 } else { other }]
+            },
+            Topology::Star if island == 0 => (1..number_of_islands).collect(),
+            Topology::Star => vec![0]
+        }
+    }
+}
+
+/// Which agents of an island make way for immigrants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Replacement {
+    /// Immigrants replace the worst agents.
+    Worst,
+    /// Immigrants replace random agents, other than the best.
+    Random,
+    /// Immigrants are added without removing any agents, so only the population's size limit
+    /// makes way for them.
+    None
+}
+
+/// The settings for migration between islands.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Migration {
+    topology: Topology,
+    interval: usize,
+    size: usize,
+    emigrant_selection: SelectionType,
+    replacement: Replacement
+}
+
+impl Migration {
+    /// By default, each island sends its 2 best agents every 10 generations, and they replace
+    /// the worst agents where they arrive.
+    pub fn new(topology: Topology) -> Self {
+        Self {
+            topology,
+            interval: 10,
+            size: 2,
+            emigrant_selection: SelectionType::HighestScore,
+            replacement: Replacement::Worst
+        }
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Sets the number of generations each island runs between migrations.
+    pub fn set_interval(&mut self, interval: usize) {
+        self.interval = interval.max(1);
+    }
+
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    /// Sets the number of agents each island sends at each migration. Each destination gets a
+    /// copy of the same emigrants, and the emigrants also stay on their island.
+    pub fn set_size(&mut self, size: usize) {
+        self.size = size;
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Sets how emigrants are chosen. HighestScore sends the best agents, and RandomAny or
+    /// Tournament keep more diversity.
+    pub fn set_emigrant_selection(&mut self, selection_type: SelectionType) {
+        self.emigrant_selection = selection_type;
+    }
+
+    pub fn emigrant_selection(&self) -> SelectionType {
+        self.emigrant_selection
+    }
+
+    pub fn set_replacement(&mut self, replacement: Replacement) {
+        self.replacement = replacement;
+    }
+
+    pub fn replacement(&self) -> Replacement {
+        self.replacement
+    }
+
+    /// Returns copies of the agents the island sends.
+    pub fn emigrants<Gene, S>(&self, population: &Population<Gene, S>, rng: &mut StdRng) -> Vec<Agent<Gene>>
+    where
+    Gene: Clone,
+    S: Score
+    {
+        if self.size == 0 || population.is_empty() {
+            return Vec::new();
+        }
+
+        Selection::with_values(self.emigrant_selection, 0.0, self.size)
+            .agents(population, rng)
+            .into_iter()
+            .map(|(_, agent)| agent.clone())
+            .collect()
+    }
+
+    /// Makes way for the immigrants as set by the replacement, then scores and adds them.
+    pub fn settle<Gene, Data, SP, S>(
+        &self,
+        population: &mut Population<Gene, S>,
+        immigrants: Vec<Agent<Gene>>,
+        data: &Data,
+        score_provider: &mut SP,
+        rng: &mut StdRng
+    )
+    where
+    Gene: Clone + Hash,
+    SP: ScoreProvider<Gene, Data, S>,
+    S: Score
+    {
+        if immigrants.is_empty() {
+            return;
+        }
+
+        let count = immigrants.len().min(population.len().saturating_sub(1));
+        match self.replacement {
+            Replacement::Worst => population.cull_worst(count),
+            Replacement::Random => population.cull_random(count, 1, rng),
+            Replacement::None => {}
+        }
+        population.add_agents(immigrants, data, score_provider, rng);
+    }
+}
+
+/// The state of an island after a migration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct IslandStats <S = u64> {
+    island: usize,
+    generation: usize,
+    best: Option<S>,
+    mean: f64,
+    population_size: usize,
+    evaluations: u64,
+    emigrants: u64,
+    immigrants: u64
+}
+
+impl <S> IslandStats <S>
+where
+S: Score
+{
+    pub(crate) fn new<Gene, Data, SP>(island: usize, generation: usize, population: &Population<Gene, S>, score_provider: &SP) -> Self
+    where
+    SP: ScoreProvider<Gene, Data, S> + ?Sized
+    {
+        let stats = GenerationStats::from_fitness(generation, population, score_provider);
+        Self {
+            island,
+            generation,
+            best: stats.map(|stats| stats.best()),
+            mean: stats.map_or(0.0, |stats| stats.mean()),
+            population_size: population.len(),
+            evaluations: 0,
+            emigrants: 0,
+            immigrants: 0
+        }
+    }
+
+    /// Returns the stats after the island has run for more generations, adding to the counts.
+    pub(crate) fn next<Gene, Data, SP>(
+        &self,
+        population: &Population<Gene, S>,
+        score_provider: &SP,
+        generations: usize,
+        evaluations: u64,
+        emigrants: u64,
+        immigrants: u64
+    ) -> Self
+    where
+    SP: ScoreProvider<Gene, Data, S> + ?Sized
+    {
+        Self {
+            evaluations,
+            emigrants: self.emigrants + emigrants,
+            immigrants: self.immigrants + immigrants,
+            ..Self::new(self.island, self.generation + generations, population, score_provider)
+        }
+    }
+
+    /// The island's index, from 0.
+    pub fn island(&self) -> usize {
+        self.island
+    }
+
+    /// The number of generations the island has run.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// The best score on the island. None if it is empty.
+    pub fn best(&self) -> Option<S> {
+        self.best
+    }
+
+    /// The mean of the scores on the island, from Score::to_f64.
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn population_size(&self) -> usize {
+        self.population_size
+    }
+
+    /// The number of calls the island has made to the fitness function, not counting its
+    /// initial population.
+    pub fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+
+    /// The number of agents the island has chosen to send. Each destination gets a copy of them.
+    pub fn emigrants(&self) -> u64 {
+        self.emigrants
+    }
+
+    /// The number of agents that have arrived at the island.
+    pub fn immigrants(&self) -> u64 {
+        self.immigrants
+    }
+}
+
+/// Returns the stats of all of the islands together, with the mean weighted by the size of each
+/// island. None if every island is empty.
+pub(crate) fn combined_stats<S>(generation: usize, islands: &[IslandStats<S>], direction: Direction) -> Option<GenerationStats<S>>
+where
+S: Score
+{
+    let best = islands.iter()
+        .filter_map(|island| island.best)
+        .fold(None, |best: Option<S>, score| match best {
+            Some(best) if !direction.is_better(score, best) => Some(best),
+            _ => Some(score)
+        })?;
+    let size: usize = islands.iter().map(|island| island.population_size).sum();
+    let total: f64 = islands.iter().map(|island| island.mean * island.population_size as f64).sum();
+    Some(GenerationStats::new(generation, best, total / size as f64))
+}
+
+/// Tells an island's thread to settle its immigrants and run until the next migration, or to stop.
+/// The thread returns its population once told to stop.
+pub(crate) enum Command<Gene> {
+    Run(Vec<Agent<Gene>>),
+    Stop
+}
+
+/// What an island thread sends at each migration.
+pub(crate) struct IslandReport<Gene, S> {
+    pub(crate) stats: IslandStats<S>,
+    pub(crate) best_agent: Option<Agent<Gene>>,
+    pub(crate) emigrants: Vec<Agent<Gene>>
+}
+
+/// The channel for commands to an island's thread, and the handle that returns its population.
+pub(crate) type Island<Gene, S> = (Sender<Command<Gene>>, JoinHandle<Population<Gene, S>>);
+
+/// Waits for a report from every island, sorted by island. Returns fewer reports if an island's
+/// thread has ended, such as by a panic in the fitness function.
+pub(crate) fn receive_reports<Gene, S>(reports: &Receiver<IslandReport<Gene, S>>, islands: &[Island<Gene, S>]) -> Vec<IslandReport<Gene, S>> {
+    let mut received = Vec::new();
+    while received.len() < islands.len() {
+        match reports.recv_timeout(Duration::from_millis(100)) {
+            Ok(report) => received.push(report),
+            Err(RecvTimeoutError::Timeout) if islands.iter().any(|(_, handle)| handle.is_finished()) => break,
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => break
+        }
+    }
+    received.sort_by_key(|report: &IslandReport<Gene, S>| report.stats.island);
+    received
+}
+
+/// Tells every island to stop and returns their populations. Panics again with the panic of any
+/// island's thread.
+pub(crate) fn stop_islands<Gene, S>(islands: Vec<Island<Gene, S>>) -> Vec<Population<Gene, S>> {
+    for (commands, _) in &islands {
+        let _ = commands.send(Command::Stop);
+    }
+    islands.into_iter()
+        .map(|(_, handle)| handle.join().unwrap_or_else(|error| panic::resume_unwind(error)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use super::super::fitness::{GeneralScoreProvider, ScoreError};
+
+    fn get_score_index(agent: &Agent<u8>, _data: &u8) -> Result<u64, ScoreError> {
+        Ok(agent.get_genes()[0] as u64)
+    }
+
+    #[test]
+    fn destinations_follow_topology() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(vec![2], Topology::Ring.destinations(1, 4, &mut rng));
+        assert_eq!(vec![0], Topology::Ring.destinations(3, 4, &mut rng));
+        assert_eq!(vec![0, 2, 3], Topology::FullyConnected.destinations(1, 4, &mut rng));
+        assert_eq!(vec![1, 2, 3], Topology::Star.destinations(0, 4, &mut rng));
+        assert_eq!(vec![0], Topology::Star.destinations(2, 4, &mut rng));
+        assert!(Topology::Ring.destinations(0, 1, &mut rng).is_empty());
+        for _ in 0..20 {
+            let destinations = Topology::Random.destinations(2, 4, &mut rng);
+            assert_eq!(1, destinations.len());
+            assert!(destinations[0] < 4 && destinations[0] != 2);
+        }
+    }
+
+    #[test]
+    fn immigrants_replace_worst_agents() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut score_provider = GeneralScoreProvider::new(get_score_index, 0);
+        let mut population = Population::new_empty(false);
+        population.add_agents((1..=5).map(|gene| Agent::from_genes(vec![gene])).collect(), &0, &mut score_provider, &mut rng);
+
+        let migration = Migration::new(Topology::Ring);
+        let emigrants = migration.emigrants(&population, &mut rng);
+        let mut genes: Vec<u8> = emigrants.iter().map(|agent| agent.get_genes()[0]).collect();
+        genes.sort();
+        assert_eq!(vec![4, 5], genes);
+
+        let immigrants = vec![Agent::from_genes(vec![9]), Agent::from_genes(vec![8])];
+        migration.settle(&mut population, immigrants, &0, &mut score_provider, &mut rng);
+        assert_eq!(vec![3, 4, 5, 8, 9], population.get_scores());
+    }
+}
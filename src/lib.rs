@@ -13,10 +13,33 @@
 // limitations under the License.
 
 extern crate rand;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(any(feature = "config", feature = "checkpoint", all(test, feature = "serde")))]
+extern crate serde_json;
+#[cfg(feature = "config")]
+extern crate toml;
+#[cfg(feature = "config")]
+extern crate serde_path_to_error;
 
 pub mod agent;
 pub mod operations;
+pub mod crossover;
+pub mod mutation;
+pub mod sampler;
 pub mod population;
 pub mod evolution;
 pub mod manager;
+pub mod island;
+pub mod termination;
+pub mod report;
+pub mod observer;
+#[cfg(feature = "checkpoint")]
+pub mod checkpoint;
+#[cfg(feature = "config")]
+pub mod config;
 pub mod fitness;
+pub mod pareto;
+pub mod parallel;
+pub mod permutation;
+pub mod real;
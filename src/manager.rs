@@ -12,11 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::fitness::{Score, ScoreProvider, GeneralScoreProvider, FitnessFunction};
-use super::population::Population;
+use super::fitness::{Direction, Score, ScoreProvider, GeneralScoreProvider, Fitness};
+use super::population::{Population, AgentKey};
+use super::pareto::{ParetoScore, ParetoScoreProvider, pareto_front};
 use super::evolution::run_iterations;
+use super::evolution::run_iterations_observed;
+use super::observer::Observer;
 use rand::{
-    distributions::{Distribution, Standard}
+    distributions::{Distribution, Standard},
+    rngs::StdRng,
+    FromEntropy,
+    Rng,
+    SeedableRng
 };
 use std::hash::Hash;
 use super::operations::{
@@ -29,98 +36,391 @@ use super::operations::{
 use std::thread; 
 use std::sync::mpsc::channel;
 use super::agent::Agent;
+use super::crossover::{CutAndSpliceCrossover, OnePointCrossover};
+use super::mutation::{LengthMutation, MutationOperator};
+use super::sampler::{GeneSampler, StandardSampler};
+use super::termination::{RunProgress, StopCondition};
+use super::report::{GenerationStats, RunReport};
+use super::island::{self, Command, Island, IslandReport, IslandStats, Migration, Topology};
+use std::sync::Arc;
 use std::collections::BTreeMap;
+#[cfg(feature = "config")]
+use super::config::ManagerConfig;
 use std::sync::mpsc::{Sender, Receiver};
+#[cfg(feature = "checkpoint")]
+use super::checkpoint::{self, Checkpoint, CheckpointError, Checkpoints, CHECKPOINT_VERSION};
+#[cfg(feature = "checkpoint")]
+use serde::Serialize;
+#[cfg(feature = "checkpoint")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "checkpoint")]
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::mem;
 
 /// Returns a Manager object that will run the genetic algorithm.
 /// Use this function if you're just writing a fitness function and not 
 /// a special ScoreProvider.
-/// fitness_function: A function you must define that determines the fitness of your agents.
+/// fitness_function: A function or closure you must define that determines the fitness of your agents.
 /// data: additional immutable data to be used by during the run of the algorithm. Could be used as
 /// a cache containing pre-calculated values or an initial state for data that will be changed when reading
 /// the genes. Just use 0 if you have no other use for this argument.
-pub fn create_manager<Gene, Data> (
-    fitness_function: FitnessFunction<Gene, Data>,
+/// Genes are drawn from the Standard distribution. Use create_manager_with_sampler to choose how
+/// genes are created instead.
+pub fn create_manager<Gene, Data, S, F> (
+    fitness_function: F,
     data: Data
-) -> Manager<Gene, Data, GeneralScoreProvider<Gene, Data>>
+) -> Manager<Gene, Data, GeneralScoreProvider<Gene, Data, S, F>, S>
 where 
 Standard: Distribution<Gene>,
 Gene: Clone + Hash + Send + 'static,
-Data: Clone + Send + 'static
+Data: Clone + Send + 'static,
+S: Score,
+F: Fitness<Gene, Data, S> + 'static
 {
-    let score_provider = GeneralScoreProvider::new(fitness_function, 25);
-    let manager = Manager::new(score_provider, data);
-    manager 
+    create_manager_with_sampler(fitness_function, StandardSampler, data)
 }
 
-pub struct Manager <Gene, Data, SP>
+/// Returns a Manager object like create_manager, but with genes created by the sampler.
+pub fn create_manager_with_sampler<Gene, Data, S, F, G> (
+    fitness_function: F,
+    sampler: G,
+    data: Data
+) -> Manager<Gene, Data, GeneralScoreProvider<Gene, Data, S, F>, S>
 where
+Gene: Clone + Hash + Send + 'static,
+Data: Clone + Send + 'static,
+S: Score,
+F: Fitness<Gene, Data, S> + 'static,
+G: GeneSampler<Gene> + 'static
+{
+    let score_provider = GeneralScoreProvider::new(fitness_function, S::default_offset());
+    Manager::with_sampler(score_provider, data, sampler)
+}
+
+/// Returns a Manager object that will run a multi-objective genetic algorithm, ranking agents
+/// in the style of NSGA-II.
+/// objectives_function: A function or closure returning a value for each objective.
+/// direction: Whether higher or lower values are better, for every objective.
+/// Genes are drawn from the Standard distribution. Use Manager::set_sampler to change this.
+pub fn create_pareto_manager<Gene, Data, F> (
+    objectives_function: F,
+    direction: Direction,
+    data: Data
+) -> Manager<Gene, Data, ParetoScoreProvider<Gene, Data, F>, ParetoScore>
+where 
 Standard: Distribution<Gene>,
 Gene: Clone + Hash + Send + 'static,
 Data: Clone + Send + 'static,
-SP: Clone + ScoreProvider<Gene, Data> + 'static
+F: Fitness<Gene, Data, Vec<f64>> + 'static
 {
-    main_population: Population<Gene>,
+    let score_provider = ParetoScoreProvider::new(objectives_function, direction);
+    Manager::new(score_provider, data)
+}
+
+/// How the rest of the initial population is filled when it has been seeded with agents.
+#[derive(Clone)]
+pub enum SeedFill <Gene> {
+    /// Fills it with agents with new random genes. This is the default.
+    Random,
+    /// Fills it with mutated copies of the seeded agents, taking each seeded agent in turn.
+    Mutated(Arc<dyn MutationOperator<Gene>>)
+}
+
+impl <Gene> SeedFill <Gene> {
+    pub fn mutated<M>(mutation_operator: M) -> Self
+    where
+    M: MutationOperator<Gene> + 'static
+    {
+        SeedFill::Mutated(Arc::new(mutation_operator))
+    }
+}
+
+/// Passes the calls from run_iterations_observed on to the manager's observers, numbering the
+/// generations from the start of the run and only passing on scores better than the best so far.
+struct RunObserver <'a, Gene, S> {
+    observers: &'a mut Vec<Box<dyn Observer<Gene, S>>>,
+    first_generation: usize,
+    direction: Direction,
+    best: &'a mut Option<S>
+}
+
+impl <'a, Gene, S> RunObserver <'a, Gene, S>
+where
+S: Score
+{
+    /// Passes on the best agent of the population if its fitness is better than the best so far.
+    fn check_best<Data, SP>(&mut self, generation: usize, population: &Population<Gene, S>, score_provider: &SP)
+    where
+    SP: ScoreProvider<Gene, Data, S>
+    {
+        if let Some((best, agent)) = population.best_fitness(score_provider) {
+            self.report_best(generation, best, agent);
+        }
+    }
+
+    fn report_best(&mut self, generation: usize, score: S, agent: &Agent<Gene>) {
+        if self.best.is_none_or(|best| self.direction.is_better(score, best)) {
+            *self.best = Some(score);
+            self.observers.on_new_best(generation, score, agent);
+        }
+    }
+}
+
+impl <'a, Gene, S> Observer<Gene, S> for RunObserver <'a, Gene, S>
+where
+S: Score
+{
+    fn on_generation_start(&mut self, generation: usize, population: &Population<Gene, S>) {
+        self.observers.on_generation_start(self.first_generation + generation, population);
+    }
+
+    fn on_operation(&mut self, generation: usize, operation_index: usize, population: &Population<Gene, S>) {
+        self.observers.on_operation(self.first_generation + generation, operation_index, population);
+    }
+
+    fn on_generation_end(&mut self, generation: usize, population: &Population<Gene, S>) {
+        self.observers.on_generation_end(self.first_generation + generation, population);
+    }
+
+    fn on_new_best(&mut self, generation: usize, score: S, agent: &Agent<Gene>) {
+        self.report_best(self.first_generation + generation, score, agent);
+    }
+
+    fn on_child_report(&mut self, child_id: u64, agents: &BTreeMap<AgentKey<S>, Agent<Gene>>) {
+        self.observers.on_child_report(child_id, agents);
+    }
+}
+
+/// The agents sent back by a child thread, along with the order the thread was spawned in, the
+/// number of fitness evaluations it made and the fitness of each agent, in the order of the agents.
+type ChildResult<Gene, S> = (u64, u64, BTreeMap<AgentKey<S>, Agent<Gene>>, Vec<Option<S>>);
+
+/// The state that run_until starts from, either a new initial population or a checkpoint.
+struct RunStart <S> {
+    progress: RunProgress<S>,
+    history: Vec<GenerationStats<S>>,
+    best: Option<S>,
+    /// The score provider's evaluations before the run, which aren't counted.
+    start_evaluations: u64,
+    /// The evaluations and time taken before the checkpoint, if resumed.
+    previous_evaluations: u64,
+    previous_elapsed: Duration
+}
+
+pub struct Manager <Gene, Data, SP, S = u64>
+where
+Gene: Clone + Hash + Send + 'static,
+Data: Clone + Send + 'static,
+SP: Clone + ScoreProvider<Gene, Data, S> + 'static,
+S: Score
+{
+    main_population: Population<Gene, S>,
     data: Data,
     number_of_genes: usize,
     strict_gene_length: bool,
+    gene_length_limits: Option<(usize, usize)>,
     initial_population_size: usize,
-    current_highest: Score,
-    agent_sender: Sender<BTreeMap<Score, Agent<Gene>>>,
-    agent_receiver: Receiver<BTreeMap<Score, Agent<Gene>>>,
+    max_population_size: usize,
+    direction: Direction,
+    agent_sender: Sender<ChildResult<Gene, S>>,
+    agent_receiver: Receiver<ChildResult<Gene, S>>,
     number_of_child_threads: u8,
+    next_child_id: u64,
     max_child_threads: u8,
-    operations: Vec<Operation<Gene, Data>>,
+    operations: Option<Vec<Operation<Gene, Data, S>>>,
     iterations_per_cycle: usize,
-    score_provider: SP
+    score_provider: SP,
+    sampler: Arc<dyn GeneSampler<Gene>>,
+    seed_agents: Vec<Agent<Gene>>,
+    seed_fill: SeedFill<Gene>,
+    observers: Vec<Box<dyn Observer<Gene, S>>>,
+    islands: usize,
+    migration: Migration,
+    island_populations: Vec<Population<Gene, S>>,
+    seed: Option<u64>,
+    rng: StdRng,
+    #[cfg(feature = "checkpoint")]
+    checkpoints: Option<Checkpoints<Gene, S>>,
+    #[cfg(feature = "checkpoint")]
+    resumed: Option<Checkpoint<Gene, S>>
 }
 
-impl <Gene, Data, SP> Manager <Gene, Data, SP>
+impl <Gene, Data, SP, S> Manager <Gene, Data, SP, S>
 where
-Standard: Distribution<Gene>,
 Gene: Clone + Hash + Send + 'static,
 Data: Clone + Send + 'static,
-SP: Clone + Send + ScoreProvider<Gene, Data>
+SP: Clone + Send + ScoreProvider<Gene, Data, S>,
+S: Score
 {
-    pub fn new(score_provider: SP, data: Data) -> Self {
-
-        let (tx, rx) = channel::<BTreeMap<Score, Agent<Gene>>>();
+    /// Creates a manager whose genes are drawn from the Standard distribution.
+    pub fn new(score_provider: SP, data: Data) -> Self
+    where
+    Standard: Distribution<Gene>
+    {
+        Self::with_sampler(score_provider, data, StandardSampler)
+    }
 
-        let operations = vec![
-            Operation::new(OperationType::Mutate, Selection::new(SelectionType::RandomAny, 0.1)),
-            Operation::new(OperationType::Crossover, Selection::new(SelectionType::HighestScore, 0.2)),
-            Operation::new(OperationType::Crossover, Selection::new(SelectionType::RandomAny, 0.2)),
-            Operation::new(OperationType::Cull, Selection::new(SelectionType::LowestScore, 0.1)),
-        ];
+    /// Creates a manager whose genes are created by the sampler, both for the initial agents and
+    /// for the default operations that add new genes.
+    pub fn with_sampler<G>(score_provider: SP, data: Data, sampler: G) -> Self
+    where
+    G: GeneSampler<Gene> + 'static
+    {
+        let (tx, rx) = channel::<ChildResult<Gene, S>>();
 
         Self {
             main_population: Population::new_empty(false),
             data: data,
             number_of_genes: 10,
-            strict_gene_length: false,
+            strict_gene_length: true,
+            gene_length_limits: None,
             initial_population_size: 100,
-            current_highest: 0,
+            max_population_size: 1000,
+            direction: Direction::Maximise,
             agent_sender: tx,
             agent_receiver: rx,
             number_of_child_threads: 0,
+            next_child_id: 0,
             max_child_threads: 3,
-            operations: operations,
+            operations: None,
             iterations_per_cycle: 100,
-            score_provider: score_provider
+            score_provider: score_provider,
+            sampler: Arc::new(sampler),
+            seed_agents: Vec::new(),
+            seed_fill: SeedFill::Random,
+            observers: Vec::new(),
+            islands: 0,
+            migration: Migration::new(Topology::Ring),
+            island_populations: Vec::new(),
+            seed: None,
+            rng: StdRng::from_entropy(),
+            #[cfg(feature = "checkpoint")]
+            checkpoints: None,
+            #[cfg(feature = "checkpoint")]
+            resumed: None
         }
     }
 
+    /// Sets the number of genes that agents start with. If not strict, the default operations
+    /// also insert and delete genes and use cut and splice crossover, so that the number of genes
+    /// can change within the gene length limits. The number of genes is strict by default.
     pub fn set_number_of_genes(&mut self, number: usize, strict: bool) {
         self.number_of_genes = number;
         self.strict_gene_length = strict;
     }
 
+    /// Sets the fewest and most genes an agent can have when the number of genes isn't strict.
+    /// Defaults to between 1 and twice the number of genes.
+    /// Panics if the minimum is 0, as operators can't work on agents without genes, or if the
+    /// minimum is above the maximum.
+    pub fn set_gene_length_limits(&mut self, min_length: usize, max_length: usize) {
+        assert!(min_length >= 1, "the minimum gene length must be at least 1");
+        assert!(min_length <= max_length, "the minimum gene length must not be above the maximum");
+        self.gene_length_limits = Some((min_length, max_length));
+    }
+
+    /// Sets how genes are created, both for the initial agents and for the default operations
+    /// that add new genes. Operations set with set_operations use their own operators.
+    pub fn set_sampler<G>(&mut self, sampler: G)
+    where
+    G: GeneSampler<Gene> + 'static
+    {
+        self.sampler = Arc::new(sampler);
+    }
+
+    /// Adds agents, such as known good solutions, to the initial population of each run.
+    /// They are scored by the score provider like any other agent, and the rest of the initial
+    /// population is filled as set with set_seed_fill.
+    pub fn seed_agents(&mut self, agents: Vec<Agent<Gene>>) {
+        self.seed_agents.extend(agents);
+    }
+
+    /// Sets how the rest of the initial population is filled when it has been seeded.
+    /// Defaults to SeedFill::Random.
+    pub fn set_seed_fill(&mut self, fill: SeedFill<Gene>) {
+        self.seed_fill = fill;
+    }
+
+    /// Adds an observer that is told about each generation of the main population, each
+    /// operation, each new best agent and each child thread that reports back.
+    pub fn add_observer<O>(&mut self, observer: O)
+    where
+    O: Observer<Gene, S> + 'static
+    {
+        self.observers.push(Box::new(observer));
+    }
+
     pub fn set_initial_population_size(&mut self, size: usize) {
         self.initial_population_size = size;
     }
 
-    pub fn set_operations(&mut self, operations: Vec<Operation<Gene, Data>>) {
-        self.operations = operations;
+    /// Sets the most agents a population can hold. Once full, new agents replace the lowest scored.
+    /// Defaults to 1000. Populations keep agents with equal scores, so without a limit they would
+    /// grow with every iteration that adds more agents than it culls.
+    pub fn set_max_population_size(&mut self, size: usize) {
+        self.max_population_size = size;
+    }
+
+    /// Sets whether higher or lower scores are better. Defaults to Direction::Maximise.
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+    }
+
+    /// Applies the settings of the configuration, leaving those it doesn't set unchanged.
+    /// Mutate operations use the manager's sampler for new genes, and Crossover operations use cut
+    /// and splice crossover if the number of genes isn't strict. The termination settings aren't
+    /// applied, so pass ManagerConfig::stop_condition to run_until.
+    #[cfg(feature = "config")]
+    pub fn apply_config(&mut self, config: &ManagerConfig<S>) {
+        if let Some(size) = config.initial_population_size {
+            self.initial_population_size = size;
+        }
+        if let Some(size) = config.max_population_size {
+            self.max_population_size = size;
+        }
+        if let Some(number) = config.number_of_genes {
+            self.number_of_genes = number;
+        }
+        if let Some(strict) = config.strict_gene_length {
+            self.strict_gene_length = strict;
+        }
+        if let Some((min_length, max_length)) = config.gene_length_limits {
+            self.set_gene_length_limits(min_length, max_length);
+        }
+        if let Some(number) = config.iterations_per_cycle {
+            self.iterations_per_cycle = number;
+        }
+        if let Some(max_number) = config.max_child_threads {
+            self.max_child_threads = max_number;
+        }
+        if let Some(direction) = config.direction {
+            self.direction = direction;
+        }
+        if let Some(seed) = config.seed {
+            self.seed = Some(seed);
+        }
+        if let Some(operations) = &config.operations {
+            let operations = operations.iter()
+                .map(|operation| {
+                    let selection = operation.to_selection();
+                    match operation.operation {
+                        OperationType::Mutate => Operation::with_sampler(OperationType::Mutate, selection, self.sample_function()),
+                        OperationType::Crossover if self.strict_gene_length =>
+                            Operation::with_crossover_operator(selection, OnePointCrossover),
+                        OperationType::Crossover => Operation::with_crossover_operator(selection, self.cut_and_splice_crossover()),
+                        OperationType::Cull => Operation::cull(selection),
+                        OperationType::RankedCull => Operation::ranked_cull(selection)
+                    }
+                })
+                .collect();
+            self.operations = Some(operations);
+        }
+    }
+
+    /// Replaces the default operations, which depend on whether the number of genes is strict.
+    pub fn set_operations(&mut self, operations: Vec<Operation<Gene, Data, S>>) {
+        self.operations = Some(operations);
     }
 
     pub fn set_max_child_threads(&mut self, max_number: u8) {
@@ -131,61 +431,1030 @@ SP: Clone + Send + ScoreProvider<Gene, Data>
         self.iterations_per_cycle = number;
     }
 
-    pub fn run(&mut self, goal: Score) {
-        self.main_population = Population::new(self.initial_population_size, self.number_of_genes, false, &self.data, &mut self.score_provider);
+    /// Runs an island model instead of child threads. Each island is a population of its own,
+    /// with the initial and maximum population size, that runs the operations in its own thread
+    /// for the whole run. Every migration interval, the islands send emigrants to each other
+    /// following the migration's topology, and the stop condition is checked.
+    /// A number of 0 goes back to child threads.
+    pub fn set_islands(&mut self, number: usize, migration: Migration) {
+        self.islands = number;
+        self.migration = migration;
+    }
+
+    /// The populations of the islands at the end of the last run. Empty unless it ran islands.
+    pub fn get_islands(&self) -> &[Population<Gene, S>] {
+        &self.island_populations
+    }
+
+    /// Makes runs reproducible, so that the same seed results in the same population.
+    /// Each child thread gets its own seed derived from this one, and the manager waits for
+    /// the child threads of each cycle so that their agents are always added in the same order.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
 
-        while self.current_highest < goal {
+    /// Runs until the best agent has reached the goal score. That is, a score of at least
+    /// the goal when maximising, or at most the goal when minimising.
+    /// This never stops if the goal can't be reached. Use run_until to set other limits.
+    pub fn run(&mut self, goal: S) -> RunReport<Gene, S> {
+        self.run_until(StopCondition::Goal(goal))
+    }
+
+    /// Runs until the condition is met, checking it after each cycle, and returns a report of
+    /// the run including the condition that was met.
+    /// After resume, the run carries on from the checkpoint instead of starting again.
+    pub fn run_until(&mut self, condition: StopCondition<S>) -> RunReport<Gene, S> {
+        if self.islands > 0 {
+            #[cfg(feature = "checkpoint")]
+            assert!(self.resumed.is_none(), "island runs can't be resumed from a checkpoint");
+            return self.run_islands(condition);
+        }
+
+        let start = Instant::now();
+        #[cfg(feature = "checkpoint")]
+        let resumed = self.resumed.take().map(|checkpoint| self.resume_run(checkpoint));
+        #[cfg(not(feature = "checkpoint"))]
+        let resumed = None;
+        let RunStart {
+            mut progress,
+            mut history,
+            mut best,
+            start_evaluations,
+            previous_evaluations,
+            previous_elapsed
+        } = match resumed {
+            Some(run_start) => run_start,
+            None => self.start_run()
+        };
+        let mut child_evaluations = previous_evaluations;
+        #[cfg(feature = "checkpoint")]
+        if let Some(checkpoints) = &mut self.checkpoints {
+            checkpoints.cycles = 0;
+        }
+
+        let operations = self.get_operations();
+        loop {
 
             if self.number_of_child_threads < self.max_child_threads {
                 for _ in 0..(self.max_child_threads - self.number_of_child_threads) {
-                    self.spawn_population_in_new_thread();
+                    self.spawn_population_in_new_thread(&operations);
                 }
             }
 
-            let cloned_population = self.main_population.clone();
-            self.main_population = run_iterations(cloned_population, self.iterations_per_cycle, &self.data, &self.operations, &mut self.score_provider);
+            // The main population runs one iteration at a time to record the stats of each
+            // generation. The last generation of the cycle is recorded once the child threads'
+            // agents have been added.
+            for iteration in 1..=self.iterations_per_cycle {
+                let population = mem::replace(&mut self.main_population, Population::new_empty(false));
+                let mut observer = RunObserver {
+                    observers: &mut self.observers,
+                    first_generation: progress.generations + iteration - 1,
+                    direction: self.direction,
+                    best: &mut best
+                };
+                self.main_population = run_iterations_observed(population, 1, &self.data, &operations, &mut self.score_provider, &mut observer, &mut self.rng);
+                if iteration < self.iterations_per_cycle {
+                    history.extend(GenerationStats::from_fitness(progress.generations + iteration, &self.main_population, &self.score_provider));
+                }
+            }
 
-            let mut check_messages = true;
-            while check_messages {
-                let result = self.agent_receiver.try_recv();
-                if result.is_ok() {
-                    for (score, agent) in result.ok().unwrap() {
-                        self.main_population.insert(score, agent);
-                    }
+            if self.seed.is_some() {
+                child_evaluations += self.wait_for_child_threads();
+            } else {
+                while let Ok(result) = self.agent_receiver.try_recv() {
                     self.number_of_child_threads -= 1;
-                } else {
-                    check_messages = false;
+                    child_evaluations += self.add_child_result(result);
                 }
             }
+            self.score_provider.rank_population(&mut self.main_population, &self.data);
+
+            let best_fitness = self.main_population.best_fitness(&self.score_provider).map(|(best, agent)| (best, agent.clone()));
+            progress.add_cycle(self.iterations_per_cycle, best_fitness.as_ref().map(|(best, _)| *best));
+            progress.evaluations = self.score_provider.evaluations() - start_evaluations + child_evaluations;
+            progress.elapsed = previous_elapsed + start.elapsed();
+            RunObserver {
+                observers: &mut self.observers,
+                first_generation: 0,
+                direction: self.direction,
+                best: &mut best
+            }.check_best(progress.generations, &self.main_population, &self.score_provider);
+            history.extend(GenerationStats::from_fitness(progress.generations, &self.main_population, &self.score_provider));
+
+            // StdRng can't be saved, so each cycle starts from a new seed that a checkpoint can
+            // hold. This is done whether or not checkpoints are written, so that both runs match.
+            let cycle_seed = self.rng.gen::<u64>();
+            self.rng = StdRng::seed_from_u64(cycle_seed);
+            #[cfg(feature = "checkpoint")]
+            self.save_checkpoint(&progress, best, cycle_seed, &history);
 
-            let (highest, _) = self.main_population.get_agents().iter().rev().next().unwrap();
-            self.current_highest = *highest;
+            if let Some(met) = condition.check(&progress) {
+                let (best_score, best_agent) = match best_fitness {
+                    Some((best, agent)) => (Some(best), Some(agent)),
+                    None => (None, None)
+                };
+                return RunReport {
+                    best_agent,
+                    best_score,
+                    generations: progress.generations,
+                    evaluations: progress.evaluations,
+                    elapsed: progress.elapsed,
+                    stop_reason: met.clone(),
+                    history,
+                    islands: Vec::new()
+                };
+            }
         }
     }
 
-    pub fn get_population(&self) -> &Population<Gene> {
+    pub fn get_population(&self) -> &Population<Gene, S> {
         return &self.main_population;
     }
 
-    fn spawn_population_in_new_thread(&mut self) {
+    pub fn get_score_provider(&self) -> &SP {
+        &self.score_provider
+    }
+
+    /// Starts a run from a new initial population.
+    fn start_run(&mut self) -> RunStart<S> {
+        let start_evaluations = self.score_provider.evaluations();
+        self.reset_rng();
+        self.main_population = self.create_initial_population();
+        self.main_population.set_direction(self.direction);
+        self.main_population.set_max_size(Some(self.max_population_size));
+        let history = GenerationStats::from_fitness(0, &self.main_population, &self.score_provider).into_iter().collect();
+        let mut best = None;
+        RunObserver {
+            observers: &mut self.observers,
+            first_generation: 0,
+            direction: self.direction,
+            best: &mut best
+        }.check_best(0, &self.main_population, &self.score_provider);
+
+        RunStart {
+            progress: RunProgress::new(self.direction),
+            history,
+            best,
+            start_evaluations,
+            previous_evaluations: 0,
+            previous_elapsed: Duration::from_secs(0)
+        }
+    }
+
+    /// Starts a run from where the checkpoint left off.
+    #[cfg(feature = "checkpoint")]
+    fn resume_run(&mut self, checkpoint: Checkpoint<Gene, S>) -> RunStart<S> {
+        self.rng = StdRng::seed_from_u64(checkpoint.rng_seed);
+        self.main_population = checkpoint.population;
+        self.main_population.set_direction(self.direction);
+        self.main_population.set_max_size(Some(self.max_population_size));
+        self.next_child_id = checkpoint.next_child_id;
+        self.score_provider.restore_fitness(checkpoint.score_cache);
+        self.score_provider.rank_population(&mut self.main_population, &self.data);
+
+        let mut progress = RunProgress::new(self.direction);
+        progress.best = checkpoint.best;
+        progress.generations = checkpoint.generations;
+        progress.cycles_without_improvement = checkpoint.cycles_without_improvement;
+        RunStart {
+            progress,
+            history: checkpoint.history,
+            best: checkpoint.observed_best,
+            // Evaluations made while resuming were already counted by the checkpoint.
+            start_evaluations: self.score_provider.evaluations(),
+            previous_evaluations: checkpoint.evaluations,
+            previous_elapsed: checkpoint.elapsed
+        }
+    }
+
+    /// Writes a checkpoint at the end of a cycle if one is due. The seed is the one the next
+    /// cycle starts from.
+    #[cfg(feature = "checkpoint")]
+    fn save_checkpoint(&mut self, progress: &RunProgress<S>, best: Option<S>, rng_seed: u64, history: &[GenerationStats<S>]) {
+        if !self.checkpoints.as_mut().is_some_and(|checkpoints| checkpoints.end_cycle()) {
+            return;
+        }
+        let checkpoint = Checkpoint {
+            version: CHECKPOINT_VERSION,
+            generations: progress.generations,
+            cycles_without_improvement: progress.cycles_without_improvement,
+            best: progress.best,
+            observed_best: best,
+            evaluations: progress.evaluations,
+            elapsed: progress.elapsed,
+            rng_seed,
+            next_child_id: self.next_child_id,
+            history: history.to_vec(),
+            score_cache: self.score_provider.cached_fitness(),
+            population: self.main_population.clone()
+        };
+        if let Some(checkpoints) = &mut self.checkpoints {
+            checkpoints.save(&checkpoint);
+        }
+    }
+
+    fn reset_rng(&mut self) {
+        self.rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy()
+        };
+    }
+
+    /// Runs the island model set with set_islands. Each island reports back at every migration,
+    /// and the manager routes the emigrants, so runs with a seed are reproducible.
+    fn run_islands(&mut self, condition: StopCondition<S>) -> RunReport<Gene, S> {
+        let start = Instant::now();
+        let start_evaluations = self.score_provider.evaluations();
+        self.reset_rng();
+        let operations = self.get_operations();
+
+        let (report_sender, reports) = channel();
+        let mut stats = Vec::new();
+        let mut islands = Vec::new();
+        let mut best = None;
+        let mut best_agent = None;
+        for index in 0..self.islands {
+            let mut population = self.create_initial_population();
+            population.set_direction(self.direction);
+            population.set_max_size(Some(self.max_population_size));
+            stats.push(IslandStats::new(index, 0, &population, &self.score_provider));
+            if let Some((score, agent)) = population.best_fitness(&self.score_provider) {
+                if best.is_none_or(|best| self.direction.is_better(score, best)) {
+                    best = Some(score);
+                    best_agent = Some(agent.clone());
+                }
+            }
+            islands.push(self.spawn_island(index, population, &operations, &report_sender));
+        }
+        drop(report_sender);
+        let initial_evaluations = self.score_provider.evaluations() - start_evaluations;
+        if let (Some(score), Some(agent)) = (best, &best_agent) {
+            self.observers.on_new_best(0, score, agent);
+        }
+
+        let mut progress = RunProgress::new(self.direction);
+        let mut history: Vec<GenerationStats<S>> = island::combined_stats(0, &stats, self.direction).into_iter().collect();
+        let mut immigrants = vec![Vec::new(); self.islands];
+        let (met, last_reports) = loop {
+            for ((commands, _), agents) in islands.iter().zip(immigrants.drain(..)) {
+                let _ = commands.send(Command::Run(agents));
+            }
+
+            let island_reports = island::receive_reports(&reports, &islands);
+            if island_reports.len() < self.islands {
+                island::stop_islands(islands);
+                panic!("An island stopped before the end of the run");
+            }
+            immigrants = vec![Vec::new(); self.islands];
+            for report in &island_reports {
+                for destination in self.migration.topology().destinations(report.stats.island(), self.islands, &mut self.rng) {
+                    immigrants[destination].extend(report.emigrants.iter().cloned());
+                }
+            }
+            stats = island_reports.iter().map(|report| report.stats).collect();
+
+            let generation = progress.generations + self.migration.interval();
+            self.observers.on_migration(generation, &stats);
+            for report in &island_reports {
+                if let (Some(score), Some(agent)) = (report.stats.best(), &report.best_agent) {
+                    if best.is_none_or(|best| self.direction.is_better(score, best)) {
+                        best = Some(score);
+                        self.observers.on_new_best(generation, score, agent);
+                    }
+                }
+            }
+
+            progress.add_cycle(self.migration.interval(), best);
+            progress.evaluations = initial_evaluations + stats.iter().map(|island| island.evaluations()).sum::<u64>();
+            progress.elapsed = start.elapsed();
+            history.extend(island::combined_stats(progress.generations, &stats, self.direction));
+            if let Some(met) = condition.check(&progress) {
+                break (met.clone(), island_reports);
+            }
+        };
+
+        self.island_populations = island::stop_islands(islands);
+
+        // The main population holds every agent of every island.
+        let mut population = Population::new_empty(false);
+        population.set_direction(self.direction);
+        for island in &self.island_populations {
+            population.set_agents(island.get_agents().clone());
+        }
+        self.score_provider.rank_population(&mut population, &self.data);
+        self.main_population = population;
+
+        // The islands' last reports hold the best agent of each island at the end of the run.
+        let mut final_best: Option<(S, Agent<Gene>)> = None;
+        for report in last_reports {
+            if let (Some(score), Some(agent)) = (report.stats.best(), report.best_agent) {
+                if final_best.as_ref().is_none_or(|(best, _)| self.direction.is_better(score, *best)) {
+                    final_best = Some((score, agent));
+                }
+            }
+        }
+        let (best_score, best_agent) = match final_best {
+            Some((best, agent)) => (Some(best), Some(agent)),
+            None => (None, None)
+        };
+        RunReport {
+            best_agent,
+            best_score,
+            generations: progress.generations,
+            evaluations: progress.evaluations,
+            elapsed: progress.elapsed,
+            stop_reason: met,
+            history,
+            islands: stats
+        }
+    }
+
+    /// Creates the initial population from the seeded agents, filling the rest of it as set with
+    /// set_seed_fill.
+    fn create_initial_population(&mut self) -> Population<Gene, S> {
+        let fill_size = self.initial_population_size.saturating_sub(self.seed_agents.len());
+        let mut agents = self.seed_agents.clone();
+        let random_size = match self.seed_fill {
+            SeedFill::Mutated(ref mutation_operator) if !self.seed_agents.is_empty() => {
+                for index in 0..fill_size {
+                    let seed_agent = &self.seed_agents[index % self.seed_agents.len()];
+                    agents.push(mutation_operator.mutate(seed_agent, &mut self.rng));
+                }
+                0
+            },
+            _ => fill_size
+        };
+
+        let mut population = Population::with_sampler(
+            random_size,
+            self.number_of_genes,
+            false,
+            self.sampler.as_ref(),
+            &self.data,
+            &mut self.score_provider,
+            &mut self.rng
+        );
+        population.add_agents(agents, &self.data, &mut self.score_provider, &mut self.rng);
+        population
+    }
+
+    fn get_operations(&self) -> Vec<Operation<Gene, Data, S>> {
+        if let Some(operations) = &self.operations {
+            return operations.clone();
+        }
+
+        let sample = self.sample_function();
+        let mut operations = vec![
+            Operation::with_sampler(OperationType::Mutate, Selection::new(SelectionType::RandomAny, 0.1), sample.clone()),
+            Operation::with_crossover_operator(Selection::new(SelectionType::HighestScore, 0.2), OnePointCrossover),
+            Operation::with_crossover_operator(Selection::new(SelectionType::RandomAny, 0.2), OnePointCrossover),
+            Operation::cull(Selection::new(SelectionType::LowestScore, 0.1)),
+        ];
+        if !self.strict_gene_length {
+            let (min_length, max_length) = self.get_gene_length_limits();
+            let crossover = self.cut_and_splice_crossover();
+            operations[1] = Operation::with_crossover_operator(Selection::new(SelectionType::HighestScore, 0.2), crossover);
+            operations[2] = Operation::with_crossover_operator(Selection::new(SelectionType::RandomAny, 0.2), crossover);
+            operations.insert(1, Operation::with_mutation_operator(
+                Selection::new(SelectionType::RandomAny, 0.1),
+                LengthMutation::with_sampler(min_length, max_length, sample)));
+        }
+        operations
+    }
+
+    fn get_gene_length_limits(&self) -> (usize, usize) {
+        self.gene_length_limits.unwrap_or((1, self.number_of_genes.saturating_mul(2).max(1)))
+    }
+
+    fn cut_and_splice_crossover(&self) -> CutAndSpliceCrossover {
+        let (min_length, max_length) = self.get_gene_length_limits();
+        CutAndSpliceCrossover::with_limits(min_length, max_length)
+    }
+
+    /// Returns a closure that draws genes from the manager's sampler, for operators that take a
+    /// sampler.
+    fn sample_function(&self) -> impl Fn(&mut StdRng) -> Gene + Clone + Send + Sync + 'static {
+        let sampler = self.sampler.clone();
+        move |rng: &mut StdRng| sampler.sample(rng)
+    }
+
+    fn spawn_population_in_new_thread(&mut self, operations: &[Operation<Gene, Data, S>]) {
         let initial_population_size = self.initial_population_size;
+        let max_population_size = self.max_population_size;
+        let direction = self.direction;
         let number_of_genes = self.number_of_genes;
         let data = self.data.clone();
-        let operations = self.operations.clone();
+        let operations = operations.to_vec();
         let iterations_per_cycle = self.iterations_per_cycle;
         let mut score_provider = self.score_provider.clone();
+        let sampler = self.sampler.clone();
+        let child_id = self.next_child_id;
+        let child_seed = self.rng.gen::<u64>();
 
         let tx = self.agent_sender.clone();
 
         thread::spawn(move || {
-            let population = run_iterations(Population::new(initial_population_size, number_of_genes, false, &data, &mut score_provider), iterations_per_cycle, &data, &operations, &mut score_provider);
+            let mut rng = StdRng::seed_from_u64(child_seed);
+            let start_evaluations = score_provider.evaluations();
+            let mut population = Population::with_sampler(
+                initial_population_size,
+                number_of_genes,
+                false,
+                sampler.as_ref(),
+                &data,
+                &mut score_provider,
+                &mut rng
+            );
+            population.set_direction(direction);
+            population.set_max_size(Some(max_population_size));
+            let population = run_iterations(population, iterations_per_cycle, &data, &operations, &mut score_provider, &mut rng);
             let population = cull_lowest_agents(population, 0.5, 1);
-            match tx.send(population.get_agents().clone()) {
-                Ok(()) => (),
-                Err(_) => () // The parent thread probably finished its run. That doesn't really matter.
-            }
+            // An error means the parent thread probably finished its run. That doesn't really matter.
+            let evaluations = score_provider.evaluations() - start_evaluations;
+            let fitness = population.get_agents().values().map(|agent| score_provider.fitness(agent)).collect();
+            let _ = tx.send((child_id, evaluations, population.get_agents().clone(), fitness));
         });
 
+        self.next_child_id += 1;
         self.number_of_child_threads += 1;
     }
-}
\ No newline at end of file
+
+    /// Runs an island in its own thread. Before each migration interval, the island waits for a
+    /// command with its immigrants.
+    fn spawn_island(
+        &mut self,
+        index: usize,
+        mut population: Population<Gene, S>,
+        operations: &[Operation<Gene, Data, S>],
+        reports: &Sender<IslandReport<Gene, S>>
+    ) -> Island<Gene, S> {
+        let data = self.data.clone();
+        let operations = operations.to_vec();
+        let mut score_provider = self.score_provider.clone();
+        let migration = self.migration;
+        let island_seed = self.rng.gen::<u64>();
+        let reports = reports.clone();
+        let (command_sender, commands) = channel::<Command<Gene>>();
+
+        let handle = thread::spawn(move || {
+            let mut rng = StdRng::seed_from_u64(island_seed);
+            let start_evaluations = score_provider.evaluations();
+            let mut stats = IslandStats::new(index, 0, &population, &score_provider);
+            while let Ok(Command::Run(immigrants)) = commands.recv() {
+                let immigrant_count = immigrants.len() as u64;
+                migration.settle(&mut population, immigrants, &data, &mut score_provider, &mut rng);
+                population = run_iterations(population, migration.interval(), &data, &operations, &mut score_provider, &mut rng);
+
+                let emigrants = migration.emigrants(&population, &mut rng);
+                stats = stats.next(&population, &score_provider, migration.interval(), score_provider.evaluations() - start_evaluations, emigrants.len() as u64, immigrant_count);
+                let report = IslandReport {
+                    stats,
+                    best_agent: population.best_fitness(&score_provider).map(|(_, agent)| agent.clone()),
+                    emigrants
+                };
+                // An error means the manager has stopped waiting for this island.
+                if reports.send(report).is_err() {
+                    break;
+                }
+            }
+            population
+        });
+        (command_sender, handle)
+    }
+
+    /// Blocks until every child thread has reported back, then adds their agents in the order
+    /// the threads were spawned. Returns the number of fitness evaluations they made.
+    fn wait_for_child_threads(&mut self) -> u64 {
+        let mut results = Vec::new();
+        while self.number_of_child_threads > 0 {
+            match self.agent_receiver.recv() {
+                Ok(result) => results.push(result),
+                Err(_) => break
+            }
+            self.number_of_child_threads -= 1;
+        }
+
+        results.sort_by_key(|(child_id, _, _, _)| *child_id);
+        let mut evaluations = 0;
+        for result in results {
+            evaluations += self.add_child_result(result);
+        }
+        evaluations
+    }
+
+    /// Adds the agents of a child thread to the main population, keeping their fitness so that
+    /// the best score doesn't include the random offset. Returns the number of fitness
+    /// evaluations the thread made.
+    fn add_child_result(&mut self, (child_id, evaluations, agents, fitness): ChildResult<Gene, S>) -> u64 {
+        self.observers.on_child_report(child_id, &agents);
+        for (agent, fitness) in agents.values().zip(fitness) {
+            if let Some(fitness) = fitness {
+                self.score_provider.add_fitness(agent, fitness);
+            }
+        }
+        self.main_population.set_agents(agents);
+        evaluations
+    }
+}
+#[cfg(feature = "checkpoint")]
+impl <Gene, Data, SP, S> Manager <Gene, Data, SP, S>
+where
+Gene: Clone + Hash + Send + Serialize + DeserializeOwned + 'static,
+Data: Clone + Send + 'static,
+SP: Clone + Send + ScoreProvider<Gene, Data, S>,
+S: Score + Serialize + DeserializeOwned
+{
+    /// Writes a checkpoint to the path at the end of every given number of cycles of run_until,
+    /// replacing the one before, so that the run can be continued with resume.
+    /// Runs with a seed continue exactly as they would have without stopping, as long as the
+    /// score provider keeps its cache in cached_fitness. Others are evaluated again.
+    /// Island runs don't write checkpoints. Panics if the number of cycles is 0.
+    pub fn checkpoint_every<P>(&mut self, cycles: usize, path: P)
+    where
+    P: AsRef<Path>
+    {
+        assert!(cycles >= 1, "checkpoints must be at least 1 cycle apart");
+        self.checkpoints = Some(Checkpoints {
+            interval: cycles,
+            path: path.as_ref().to_path_buf(),
+            write: Box::new(|checkpoint, path| checkpoint::write(checkpoint, path)),
+            cycles: 0,
+            error: None
+        });
+    }
+
+    /// Reads a checkpoint written by checkpoint_every, so that the next run_until carries on
+    /// from it. The manager's settings, such as its operations, aren't in the checkpoint, so
+    /// they should be set as they were for the run that wrote it.
+    /// Island runs can't be resumed, so run_until panics if islands are set after resume.
+    pub fn resume<P>(&mut self, path: P) -> Result<(), CheckpointError>
+    where
+    P: AsRef<Path>
+    {
+        self.resumed = Some(checkpoint::read(path.as_ref())?);
+        Ok(())
+    }
+
+    /// The error of the last checkpoint written, if writing it failed. The run goes on.
+    pub fn checkpoint_error(&self) -> Option<&CheckpointError> {
+        self.checkpoints.as_ref().and_then(|checkpoints| checkpoints.error.as_ref())
+    }
+}
+
+impl <Gene, Data, SP> Manager <Gene, Data, SP, ParetoScore>
+where
+Gene: Clone + Hash + Send + 'static,
+Data: Clone + Send + 'static,
+SP: Clone + Send + ScoreProvider<Gene, Data, ParetoScore>
+{
+    /// Returns the agents on the Pareto front of the main population, most isolated first.
+    pub fn get_pareto_front(&self) -> Vec<&Agent<Gene>> {
+        pareto_front(&self.main_population).into_iter().map(|(_, agent)| agent).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::fitness::{FloatScore, ScoreError};
+    use super::super::pareto::dominates;
+    use super::super::mutation::RemoveInsertMutation;
+    use super::super::sampler::ChoiceSampler;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+
+    fn get_score_index(agent: &Agent<u8>, _data: &u8) -> Result<u64, ScoreError> {
+        Ok(agent.get_genes().iter().map(|gene| *gene as u64).sum())
+    }
+
+    fn run_seeded(seed: u64) -> Vec<u64> {
+        let mut manager = create_manager(get_score_index, 0);
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.set_max_population_size(50);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(seed);
+        manager.run(0);
+        manager.get_population().get_scores()
+    }
+
+    #[test]
+    fn run_same_seed_same_population() {
+        assert_eq!(run_seeded(7), run_seeded(7));
+    }
+
+    fn get_objectives(agent: &Agent<u8>, _data: &u8) -> Result<Vec<f64>, ScoreError> {
+        let genes = agent.get_genes();
+        Ok(vec![genes[0] as f64, 255.0 - genes[0] as f64 + genes[1] as f64])
+    }
+
+    #[test]
+    fn get_pareto_front_returns_non_dominated_agents() {
+        let mut manager = create_pareto_manager(get_objectives, Direction::Maximise, 0);
+        manager.set_number_of_genes(2, true);
+        manager.set_initial_population_size(20);
+        manager.set_max_population_size(50);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(3);
+        manager.run(ParetoScore::new(0, 0.0));
+
+        let front = manager.get_pareto_front();
+        assert!(!front.is_empty());
+        let score_provider = manager.get_score_provider();
+        for agent in front.iter() {
+            let objectives = score_provider.objectives(agent).unwrap();
+            assert!(!manager.get_population().get_agents().values()
+                .any(|other| dominates(score_provider.objectives(other).unwrap(), objectives, Direction::Maximise)));
+        }
+    }
+
+    fn get_length_score(agent: &Agent<u8>, _data: &u8) -> Result<u64, ScoreError> {
+        Ok(agent.get_genes().len() as u64)
+    }
+
+    #[test]
+    fn run_without_strict_number_of_genes_changes_length() {
+        let mut manager = create_manager(get_length_score, 0);
+        manager.set_number_of_genes(4, false);
+        manager.set_gene_length_limits(2, 7);
+        manager.set_initial_population_size(10);
+        manager.set_max_population_size(50);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(1);
+        manager.run(7);
+
+        let lengths: Vec<usize> = manager.get_population().get_agents().values()
+            .map(|agent| agent.get_genes().len())
+            .collect();
+        assert!(lengths.iter().all(|length| *length >= 2 && *length <= 7));
+        assert!(lengths.contains(&7));
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_gene_length_limits_rejects_minimum_of_zero() {
+        let mut manager = create_manager(get_length_score, 0);
+        manager.set_gene_length_limits(0, 7);
+    }
+
+    #[test]
+    fn run_with_sampler_only_uses_sampled_genes() {
+        let mut manager = create_manager_with_sampler(get_score_index, ChoiceSampler::new(&[1, 2, 3]), 0);
+        manager.set_number_of_genes(4, false);
+        manager.set_initial_population_size(10);
+        manager.set_max_population_size(50);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(2);
+        manager.run(12);
+
+        assert!(manager.get_population().get_agents().values()
+            .all(|agent| agent.get_genes().iter().all(|gene| (1..=3).contains(gene))));
+    }
+
+    #[test]
+    fn run_keeps_seeded_agent() {
+        let mut manager = create_manager(get_score_index, 0);
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(4);
+        manager.seed_agents(vec![Agent::from_genes(vec![255; 4])]);
+        manager.run(1020);
+
+        assert!(manager.get_population().get_agents().values().any(|agent| agent.get_genes() == &vec![255; 4]));
+    }
+
+    #[test]
+    fn initial_population_filled_with_mutated_seeds() {
+        let mut manager = create_manager(get_score_index, 0);
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.seed_agents(vec![Agent::from_genes(vec![1; 4])]);
+        manager.set_seed_fill(SeedFill::mutated(RemoveInsertMutation::with_sampler(1, |_: &mut StdRng| 2)));
+
+        let population = manager.create_initial_population();
+        assert_eq!(10, population.len());
+        let mutated = population.get_agents().values()
+            .filter(|agent| agent.get_genes().iter().filter(|gene| **gene == 2).count() == 1)
+            .count();
+        assert_eq!(9, mutated);
+    }
+
+    #[test]
+    fn run_until_stops_at_other_condition_when_goal_unreachable() {
+        let mut manager = create_manager(get_score_index, 0);
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(5);
+        let condition = StopCondition::Goal(2000).or(StopCondition::MaxGenerations(20));
+        match manager.run_until(condition).stop_reason() {
+            StopCondition::MaxGenerations(20) => {},
+            met => panic!("unexpected condition {:?}", met)
+        }
+    }
+
+    #[test]
+    fn run_until_counts_evaluations_of_child_threads() {
+        let calls = Arc::new(AtomicU64::new(0));
+        let counter = calls.clone();
+        let fitness = move |agent: &Agent<u8>, data: &u8| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            get_score_index(agent, data)
+        };
+        let mut manager = create_manager(fitness, 0);
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(6);
+        manager.run_until(StopCondition::MaxEvaluations(500));
+
+        // Seeded runs wait for their child threads, so every call has been made by now.
+        let calls = calls.load(Ordering::SeqCst);
+        assert!(calls >= 500);
+        assert!(manager.get_score_provider().evaluations() < calls);
+    }
+
+    #[test]
+    fn run_reports_best_agent_and_history() {
+        let mut manager = create_manager(get_score_index, 0);
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(8);
+        let report = manager.run_until(StopCondition::MaxGenerations(15));
+
+        // The scores in the population include the random offset, but the report doesn't.
+        let best = manager.get_population().get_agents().values()
+            .map(|agent| get_score_index(agent, &0).unwrap())
+            .max();
+        assert_eq!(best, report.best_score());
+        assert_eq!(best, get_score_index(report.best_agent().unwrap(), &0).ok());
+        assert_eq!(15, report.generations());
+        assert!(report.evaluations() > 0);
+
+        let generations: Vec<usize> = report.history().iter().map(|stats| stats.generation()).collect();
+        assert_eq!((0..=15).collect::<Vec<usize>>(), generations);
+        assert_eq!(best, Some(report.history()[15].best()));
+    }
+
+    fn get_constant_score(_agent: &Agent<u8>, _data: &u8) -> Result<u64, ScoreError> {
+        Ok(10)
+    }
+
+    #[test]
+    fn run_until_compares_goal_without_random_offset() {
+        let mut manager = create_manager(get_constant_score, 0);
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(8);
+        let report = manager.run_until(StopCondition::Goal(20).or(StopCondition::MaxGenerations(10)));
+
+        match report.stop_reason() {
+            StopCondition::MaxGenerations(10) => {},
+            met => panic!("unexpected condition {:?}", met)
+        }
+        assert_eq!(Some(10), report.best_score());
+        assert!(report.history().iter().all(|stats| stats.best() == 10 && stats.mean() == 10.0));
+    }
+
+    fn get_score_or_nan(agent: &Agent<u8>, _data: &u8) -> Result<FloatScore, ScoreError> {
+        if agent.get_genes().contains(&0) {
+            return Ok(FloatScore(f64::NAN));
+        }
+        Ok(FloatScore(agent.get_genes().iter().map(|gene| *gene as f64).sum()))
+    }
+
+    #[test]
+    fn run_until_never_reaches_goal_with_nan_fitness() {
+        let mut manager = create_manager(get_score_or_nan, 0);
+        manager.set_number_of_genes(2, true);
+        manager.set_initial_population_size(50);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(4);
+        manager.seed_agents(vec![Agent::from_genes(vec![0, 0]), Agent::from_genes(vec![0, 9])]);
+        let report = manager.run_until(StopCondition::Goal(FloatScore(1000.0)).or(StopCondition::MaxGenerations(10)));
+
+        match report.stop_reason() {
+            StopCondition::MaxGenerations(10) => {},
+            met => panic!("unexpected condition {:?}", met)
+        }
+        assert!(report.best_score().is_some_and(|best| !best.value().is_nan()));
+        assert!(manager.get_population().get_agents().values().all(|agent| !agent.get_genes().contains(&0)));
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedObserver {
+        ends: Arc<Mutex<Vec<usize>>>,
+        bests: Arc<Mutex<Vec<u64>>>,
+        child_reports: Arc<Mutex<usize>>
+    }
+
+    impl Observer<u8> for SharedObserver {
+        fn on_generation_end(&mut self, generation: usize, _population: &Population<u8>) {
+            self.ends.lock().unwrap().push(generation);
+        }
+
+        fn on_new_best(&mut self, _generation: usize, score: u64, _agent: &Agent<u8>) {
+            self.bests.lock().unwrap().push(score);
+        }
+
+        fn on_child_report(&mut self, _child_id: u64, _agents: &BTreeMap<AgentKey<u64>, Agent<u8>>) {
+            *self.child_reports.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn run_tells_observers_about_progress() {
+        let observer = SharedObserver::default();
+        let mut manager = create_manager(get_score_index, 0);
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(9);
+        manager.add_observer(observer.clone());
+        let report = manager.run_until(StopCondition::MaxGenerations(10));
+
+        assert_eq!((1..=10).collect::<Vec<usize>>(), *observer.ends.lock().unwrap());
+        let bests = observer.bests.lock().unwrap();
+        assert!(bests.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(report.best_score(), bests.last().cloned());
+        assert_eq!(6, *observer.child_reports.lock().unwrap());
+    }
+
+    fn run_islands_seeded(seed: u64) -> (Vec<Vec<u64>>, RunReport<u8>) {
+        let mut migration = Migration::new(Topology::Ring);
+        migration.set_interval(5);
+        migration.set_size(2);
+        let mut manager = create_manager(get_score_index, 0);
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.set_max_population_size(30);
+        manager.set_islands(3, migration);
+        manager.set_seed(seed);
+        let report = manager.run_until(StopCondition::MaxGenerations(20));
+        assert_eq!(manager.get_population().len(), manager.get_islands().iter().map(|island| island.len()).sum::<usize>());
+        (manager.get_islands().iter().map(|island| island.get_scores()).collect(), report)
+    }
+
+    #[test]
+    fn run_islands_migrates_and_reports_each_island() {
+        let (scores, report) = run_islands_seeded(6);
+        assert_eq!(3, scores.len());
+        assert_eq!(20, report.generations());
+        assert_eq!(5, report.history().len());
+        assert_eq!(3, report.islands().len());
+        for (index, island) in report.islands().iter().enumerate() {
+            assert_eq!(index, island.island());
+            assert_eq!(20, island.generation());
+            assert_eq!(8, island.emigrants());
+            // The emigrants of the last migration are never delivered.
+            assert_eq!(6, island.immigrants());
+        }
+        assert_eq!(report.best_score(), report.islands().iter().filter_map(|island| island.best()).max());
+    }
+
+    #[test]
+    fn run_islands_same_seed_same_islands() {
+        assert_eq!(run_islands_seeded(8).0, run_islands_seeded(8).0);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn apply_config_sets_operations_and_run_settings() {
+        let config: ManagerConfig = ManagerConfig::from_toml(r#"
+            initial_population_size = 10
+            number_of_genes = 4
+            strict_gene_length = true
+            iterations_per_cycle = 5
+            max_child_threads = 1
+            seed = 3
+
+            [[operations]]
+            operation = "Mutate"
+            selection = "RandomAny"
+            proportion = 0.2
+
+            [[operations]]
+            operation = "Cull"
+            selection = "LowestScore"
+            proportion = 0.1
+
+            [termination]
+            max_generations = 10
+        "#).unwrap();
+        let mut manager = create_manager_with_sampler(get_score_index, ChoiceSampler::new(&[1, 2]), 0);
+        manager.apply_config(&config);
+        let report = manager.run_until(config.stop_condition().unwrap());
+
+        assert_eq!(10, report.generations());
+        assert_eq!(2, manager.get_operations().len());
+        assert!(manager.get_population().get_agents().values()
+            .all(|agent| agent.get_genes().len() == 4 && agent.get_genes().iter().all(|gene| *gene == 1 || *gene == 2)));
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn apply_config_without_strict_number_of_genes_uses_cut_and_splice_crossover() {
+        let config: ManagerConfig = ManagerConfig::from_toml(r#"
+            initial_population_size = 10
+            number_of_genes = 4
+            strict_gene_length = false
+            gene_length_limits = [2, 7]
+            iterations_per_cycle = 5
+            max_child_threads = 1
+            seed = 3
+
+            [[operations]]
+            operation = "Crossover"
+            selection = "RandomAny"
+            proportion = 0.5
+
+            [[operations]]
+            operation = "Cull"
+            selection = "LowestScore"
+            proportion = 0.1
+        "#).unwrap();
+        let mut manager = create_manager(get_length_score, 0);
+        manager.apply_config(&config);
+        manager.run_until(StopCondition::MaxGenerations(5));
+
+        let lengths: Vec<usize> = manager.get_population().get_agents().values()
+            .map(|agent| agent.get_genes().len())
+            .collect();
+        assert!(lengths.iter().all(|length| *length >= 2 && *length <= 7));
+        assert!(lengths.iter().any(|length| *length != 4));
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn checkpoint_manager() -> Manager<u8, u8, GeneralScoreProvider<u8, u8>> {
+        let mut manager: Manager<u8, u8, GeneralScoreProvider<u8, u8>> = create_manager(get_score_index, 0);
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.set_max_population_size(50);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(3);
+        manager
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn agents_of<SP>(manager: &Manager<u8, u8, SP>) -> Vec<(AgentKey<u64>, Vec<u8>)>
+    where
+    SP: Clone + Send + ScoreProvider<u8, u8>
+    {
+        manager.get_population().get_agents().iter()
+            .map(|(key, agent)| (*key, agent.get_genes().clone()))
+            .collect()
+    }
+
+    #[cfg(feature = "checkpoint")]
+    #[test]
+    fn resume_continues_seeded_run_from_checkpoint() {
+        let path = std::env::temp_dir().join(format!("aristeia-resume-{}.json", std::process::id()));
+
+        let mut uninterrupted = checkpoint_manager();
+        let expected = uninterrupted.run_until(StopCondition::MaxGenerations(30));
+
+        let mut interrupted = checkpoint_manager();
+        interrupted.checkpoint_every(1, &path);
+        interrupted.run_until(StopCondition::MaxGenerations(15));
+        assert!(interrupted.checkpoint_error().is_none());
+
+        let mut resumed = checkpoint_manager();
+        resumed.resume(&path).unwrap();
+        let report = resumed.run_until(StopCondition::MaxGenerations(30));
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(agents_of(&uninterrupted), agents_of(&resumed));
+        assert_eq!(expected.history(), report.history());
+        assert_eq!(expected.best_score(), report.best_score());
+        assert_eq!(expected.evaluations(), report.evaluations());
+        assert_eq!(30, report.generations());
+    }
+
+    #[cfg(feature = "checkpoint")]
+    #[test]
+    fn resume_rejects_other_versions() {
+        let path = std::env::temp_dir().join(format!("aristeia-version-{}.json", std::process::id()));
+        std::fs::write(&path, "{\"version\": 0}").unwrap();
+        let error = checkpoint_manager().resume(&path).unwrap_err();
+        let _ = std::fs::remove_file(&path);
+        assert!(error.message().contains("checkpoint version 0"));
+    }
+
+    #[cfg(feature = "checkpoint")]
+    #[test]
+    #[should_panic(expected = "island runs can't be resumed")]
+    fn resume_rejects_island_runs() {
+        let path = std::env::temp_dir().join(format!("aristeia-islands-{}.json", std::process::id()));
+        let mut manager = checkpoint_manager();
+        manager.checkpoint_every(1, &path);
+        manager.run_until(StopCondition::MaxGenerations(5));
+
+        let mut resumed = checkpoint_manager();
+        resumed.resume(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        resumed.set_islands(2, Migration::new(Topology::Ring));
+        resumed.run_until(StopCondition::MaxGenerations(10));
+    }
+}
@@ -0,0 +1,594 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::agent::Agent;
+use super::permutation::{swap_genes, move_gene, invert_section};
+use super::sampler::{GeneSampler, StandardSampler};
+use std::hash::Hash;
+use rand::{
+    rngs::StdRng,
+    Rng,
+};
+
+/// Creates a mutated copy of an agent.
+/// Implemented for any function or closure with the same signature as agent::mutate, so the
+/// functions in the permutation module can be used directly. Implement it for your own types
+/// to use mutation that is specific to your genes.
+pub trait MutationOperator <Gene>: Send + Sync {
+    fn mutate(&self, agent: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene>;
+}
+
+impl <Gene, F> MutationOperator<Gene> for F
+where
+F: Fn(&Agent<Gene>, &mut StdRng) -> Agent<Gene> + Send + Sync
+{
+    fn mutate(&self, agent: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        self(agent, rng)
+    }
+}
+
+/// A gene that can be converted to and from an f64, for mutations that change genes by an amount.
+/// Integer genes are rounded, and saturate at their minimum and maximum.
+pub trait NumericGene: Copy {
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Self;
+}
+
+macro_rules! impl_numeric_gene {
+    ($($gene:ty),*) => {
+        $(
+            impl NumericGene for $gene {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn from_f64(value: f64) -> Self {
+                    value.round() as $gene
+                }
+            }
+        )*
+    };
+}
+
+impl_numeric_gene!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl NumericGene for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+
+impl NumericGene for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+/// Removes a random gene and inserts a new gene from the sampler at a random position, for a
+/// number of rounds. This is the default for Mutate operations, with five rounds.
+#[derive(Clone, Copy, Debug)]
+pub struct RemoveInsertMutation <G = StandardSampler> {
+    rounds: usize,
+    sampler: G
+}
+
+impl RemoveInsertMutation {
+    pub fn new(rounds: usize) -> Self {
+        Self::with_sampler(rounds, StandardSampler)
+    }
+}
+
+impl <G> RemoveInsertMutation <G> {
+    pub fn with_sampler(rounds: usize, sampler: G) -> Self {
+        Self {
+            rounds,
+            sampler
+        }
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+}
+
+impl Default for RemoveInsertMutation {
+    fn default() -> Self {
+        Self::new(5)
+    }
+}
+
+impl <Gene, G> MutationOperator<Gene> for RemoveInsertMutation <G>
+where
+Gene: Clone + Hash,
+G: GeneSampler<Gene>
+{
+    fn mutate(&self, agent: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        let mut child = agent.clone();
+        child.remove_and_insert_genes(self.rounds, rng, |rng| self.sampler.sample(rng));
+        child
+    }
+}
+
+/// Replaces each gene with a new gene from the sampler, with a probability.
+/// The probability is 1 / the number of genes by default, so one gene is replaced on average.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResampleMutation <G = StandardSampler> {
+    probability: Option<f64>,
+    sampler: G
+}
+
+impl ResampleMutation {
+    pub fn new() -> Self {
+        Self::with_sampler(StandardSampler)
+    }
+
+    pub fn with_probability(probability: f64) -> Self {
+        let mut mutation = Self::new();
+        mutation.set_probability(probability);
+        mutation
+    }
+}
+
+impl <G> ResampleMutation <G> {
+    pub fn with_sampler(sampler: G) -> Self {
+        Self {
+            probability: None,
+            sampler
+        }
+    }
+
+    pub fn set_probability(&mut self, probability: f64) {
+        self.probability = Some(probability);
+    }
+
+    pub fn probability(&self) -> Option<f64> {
+        self.probability
+    }
+}
+
+impl <Gene, G> MutationOperator<Gene> for ResampleMutation <G>
+where
+Gene: Clone + Hash,
+G: GeneSampler<Gene>
+{
+    fn mutate(&self, agent: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        let mut genes = agent.get_genes().clone();
+        let probability = get_probability(self.probability, genes.len());
+        for gene in genes.iter_mut() {
+            if rng.gen_bool(probability) {
+                *gene = self.sampler.sample(rng);
+            }
+        }
+
+        create_child(agent, genes)
+    }
+}
+
+/// Swaps two random genes, a number of times.
+#[derive(Clone, Copy, Debug)]
+pub struct SwapMutation {
+    swaps: usize
+}
+
+impl SwapMutation {
+    pub fn new(swaps: usize) -> Self {
+        Self {
+            swaps
+        }
+    }
+
+    pub fn swaps(&self) -> usize {
+        self.swaps
+    }
+}
+
+impl Default for SwapMutation {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl <Gene> MutationOperator<Gene> for SwapMutation
+where
+Gene: Clone + Hash
+{
+    fn mutate(&self, agent: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        let mut genes = agent.get_genes().clone();
+        if genes.len() < 2 {
+            return agent.clone();
+        }
+        for _ in 0..self.swaps {
+            swap_genes(&mut genes, rng);
+        }
+
+        create_child(agent, genes)
+    }
+}
+
+/// Moves a random gene to a random position, a number of times.
+#[derive(Clone, Copy, Debug)]
+pub struct InsertionMutation {
+    moves: usize
+}
+
+impl InsertionMutation {
+    pub fn new(moves: usize) -> Self {
+        Self {
+            moves
+        }
+    }
+
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+}
+
+impl Default for InsertionMutation {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl <Gene> MutationOperator<Gene> for InsertionMutation
+where
+Gene: Clone + Hash
+{
+    fn mutate(&self, agent: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        let mut genes = agent.get_genes().clone();
+        if genes.len() < 2 {
+            return agent.clone();
+        }
+        for _ in 0..self.moves {
+            move_gene(&mut genes, rng);
+        }
+
+        create_child(agent, genes)
+    }
+}
+
+/// Reverses the order of a random section of genes, a number of times.
+#[derive(Clone, Copy, Debug)]
+pub struct InversionMutation {
+    inversions: usize
+}
+
+impl InversionMutation {
+    pub fn new(inversions: usize) -> Self {
+        Self {
+            inversions
+        }
+    }
+
+    pub fn inversions(&self) -> usize {
+        self.inversions
+    }
+}
+
+impl Default for InversionMutation {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl <Gene> MutationOperator<Gene> for InversionMutation
+where
+Gene: Clone + Hash
+{
+    fn mutate(&self, agent: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        let mut genes = agent.get_genes().clone();
+        if genes.len() < 2 {
+            return agent.clone();
+        }
+        for _ in 0..self.inversions {
+            invert_section(&mut genes, rng);
+        }
+
+        create_child(agent, genes)
+    }
+}
+
+/// Changes the number of genes by inserting new genes from the sampler at random positions, or
+/// deleting random genes, with an equal chance of each. Never grows an agent beyond the maximum length or
+/// shrinks it below the minimum, and agents already outside of the limits are brought towards them.
+/// Makes one change by default.
+#[derive(Clone, Copy, Debug)]
+pub struct LengthMutation <G = StandardSampler> {
+    min_length: usize,
+    max_length: usize,
+    changes: usize,
+    sampler: G
+}
+
+impl LengthMutation {
+    /// Panics if the minimum is 0, as operators can't work on agents without genes, or if the
+    /// minimum is above the maximum.
+    pub fn new(min_length: usize, max_length: usize) -> Self {
+        Self::with_sampler(min_length, max_length, StandardSampler)
+    }
+}
+
+impl <G> LengthMutation <G> {
+    /// Panics if the minimum is 0 or above the maximum, as with new.
+    pub fn with_sampler(min_length: usize, max_length: usize, sampler: G) -> Self {
+        assert!(min_length >= 1, "the minimum gene length must be at least 1");
+        assert!(min_length <= max_length, "the minimum gene length must not be above the maximum");
+        Self {
+            min_length,
+            max_length,
+            changes: 1,
+            sampler
+        }
+    }
+
+    /// Sets the number of genes inserted or deleted each time an agent is mutated.
+    pub fn set_changes(&mut self, changes: usize) {
+        self.changes = changes;
+    }
+
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    pub fn min_length(&self) -> usize {
+        self.min_length
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl <Gene, G> MutationOperator<Gene> for LengthMutation <G>
+where
+Gene: Clone + Hash,
+G: GeneSampler<Gene>
+{
+    fn mutate(&self, agent: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        let mut genes = agent.get_genes().clone();
+        for _ in 0..self.changes {
+            let can_grow = genes.len() < self.max_length;
+            let can_shrink = genes.len() > self.min_length && !genes.is_empty();
+            let grow = match (can_grow, can_shrink) {
+                (false, false) => break,
+                (true, true) => rng.gen::<bool>(),
+                (can_grow, _) => can_grow
+            };
+            if grow {
+                let position = rng.gen_range(0, genes.len() + 1);
+                genes.insert(position, self.sampler.sample(rng));
+            } else {
+                genes.remove(rng.gen_range(0, genes.len()));
+            }
+        }
+
+        create_child(agent, genes)
+    }
+}
+
+/// Adds a random amount, up to the step either side, to each gene with a probability.
+/// The probability is 1 / the number of genes by default.
+/// Genes are unbounded, so use GaussianMutation from the real module for genes with bounds.
+#[derive(Clone, Copy, Debug)]
+pub struct CreepMutation {
+    step: f64,
+    probability: Option<f64>
+}
+
+impl CreepMutation {
+    pub fn new(step: f64) -> Self {
+        Self {
+            step,
+            probability: None
+        }
+    }
+
+    pub fn with_probability(step: f64, probability: f64) -> Self {
+        Self {
+            step,
+            probability: Some(probability)
+        }
+    }
+
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    pub fn probability(&self) -> Option<f64> {
+        self.probability
+    }
+}
+
+impl <Gene> MutationOperator<Gene> for CreepMutation
+where
+Gene: NumericGene + Hash
+{
+    fn mutate(&self, agent: &Agent<Gene>, rng: &mut StdRng) -> Agent<Gene> {
+        let mut genes = agent.get_genes().clone();
+        let probability = get_probability(self.probability, genes.len());
+        let step = self.step.abs();
+        if step == 0.0 {
+            return agent.clone();
+        }
+        for gene in genes.iter_mut() {
+            if rng.gen_bool(probability) {
+                *gene = Gene::from_f64(gene.to_f64() + rng.gen_range(-step, step));
+            }
+        }
+
+        create_child(agent, genes)
+    }
+}
+
+fn get_probability(probability: Option<f64>, number_of_genes: usize) -> f64 {
+    let probability = probability.unwrap_or(1.0 / number_of_genes.max(1) as f64);
+    probability.clamp(0.0, 1.0)
+}
+
+fn create_child<Gene>(parent: &Agent<Gene>, genes: Vec<Gene>) -> Agent<Gene>
+where
+Gene: Clone + Hash
+{
+    let mut child = parent.clone();
+    child.set_genes(genes);
+    child
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn agent() -> Agent<i32> {
+        let mut agent = Agent::new();
+        agent.set_genes((0..20).collect());
+        agent
+    }
+
+    fn count_changed(agent: &Agent<i32>) -> usize {
+        agent.get_genes().iter().enumerate().filter(|(index, gene)| **gene != *index as i32).count()
+    }
+
+    #[test]
+    fn remove_insert_mutation_keeps_length() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let child = RemoveInsertMutation::default().mutate(&agent(), &mut rng);
+        assert_eq!(20, child.get_genes().len());
+        assert!(!child.has_same_genes(&agent()));
+    }
+
+    #[test]
+    fn mutations_handle_one_gene() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut agent: Agent<i32> = Agent::new();
+        agent.set_genes(vec![3]);
+        let operators: Vec<Box<dyn MutationOperator<i32>>> = vec![
+            Box::new(RemoveInsertMutation::default()),
+            Box::new(ResampleMutation::new()),
+            Box::new(SwapMutation::default()),
+            Box::new(InsertionMutation::default()),
+            Box::new(InversionMutation::default()),
+            Box::new(CreepMutation::new(2.0))
+        ];
+
+        for operator in operators {
+            assert_eq!(1, operator.mutate(&agent, &mut rng).get_genes().len());
+        }
+    }
+
+    #[test]
+    fn resample_mutation_follows_probability() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let child = ResampleMutation::with_probability(0.0).mutate(&agent(), &mut rng);
+        assert!(child.has_same_genes(&agent()));
+
+        let mut agent: Agent<u64> = Agent::new();
+        agent.set_genes(vec![0; 20]);
+        let child = ResampleMutation::with_probability(1.0).mutate(&agent, &mut rng);
+        assert!(child.get_genes().iter().all(|gene| *gene != 0));
+    }
+
+    #[test]
+    fn more_swaps_change_more_genes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut few = 0;
+        let mut many = 0;
+        for _ in 0..20 {
+            few += count_changed(&SwapMutation::new(1).mutate(&agent(), &mut rng));
+            many += count_changed(&SwapMutation::new(5).mutate(&agent(), &mut rng));
+        }
+        assert!(many > few);
+    }
+
+    #[test]
+    fn reordering_mutations_keep_genes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let operators: Vec<Box<dyn MutationOperator<i32>>> = vec![
+            Box::new(SwapMutation::new(3)),
+            Box::new(InsertionMutation::new(3)),
+            Box::new(InversionMutation::new(3))
+        ];
+
+        for operator in operators {
+            let mut genes = operator.mutate(&agent(), &mut rng).get_genes().clone();
+            genes.sort();
+            assert_eq!(agent().get_genes(), &genes);
+        }
+    }
+
+    #[test]
+    fn length_mutation_stays_within_limits() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut mutation = LengthMutation::new(18, 23);
+        mutation.set_changes(3);
+        let mut lengths = Vec::new();
+        let mut agent = agent();
+        for _ in 0..50 {
+            agent = mutation.mutate(&agent, &mut rng);
+            lengths.push(agent.get_genes().len());
+        }
+        assert!(lengths.iter().all(|length| *length >= 18 && *length <= 23));
+        assert!(lengths.contains(&18) && lengths.contains(&23));
+    }
+
+    #[test]
+    fn length_mutation_brings_agents_within_limits() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let child = LengthMutation::new(1, 10).mutate(&agent(), &mut rng);
+        assert_eq!(19, child.get_genes().len());
+        let child = LengthMutation::new(30, 40).mutate(&agent(), &mut rng);
+        assert_eq!(21, child.get_genes().len());
+    }
+
+    #[test]
+    fn length_mutation_never_empties_agents() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut mutation = LengthMutation::new(1, 3);
+        mutation.set_changes(5);
+        let mut agent = Agent::from_genes(vec![1u8, 2]);
+        for _ in 0..20 {
+            agent = mutation.mutate(&agent, &mut rng);
+            assert!(!agent.get_genes().is_empty());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn length_mutation_rejects_minimum_of_zero() {
+        LengthMutation::new(0, 10);
+    }
+
+    #[test]
+    #[should_panic]
+    fn length_mutation_rejects_minimum_above_maximum() {
+        LengthMutation::new(11, 10);
+    }
+
+    #[test]
+    fn creep_mutation_stays_within_step() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let child = CreepMutation::with_probability(3.0, 1.0).mutate(&agent(), &mut rng);
+        for (index, gene) in child.get_genes().iter().enumerate() {
+            assert!((*gene - index as i32).abs() <= 3);
+        }
+        assert!(count_changed(&child) > 0);
+    }
+}
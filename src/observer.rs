@@ -0,0 +1,92 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::agent::Agent;
+use super::fitness::Score;
+use super::island::IslandStats;
+use super::population::{Population, AgentKey};
+use std::collections::BTreeMap;
+
+/// Watches a run while it executes, for logging progress, plotting or exporting metrics.
+/// Every method does nothing by default, so implement only those you need.
+/// Generations are numbered from 1, as generation 0 is the initial population. To stop a run
+/// early, set the flag of a StopCondition::Cancelled.
+pub trait Observer <Gene, S = u64>: Send {
+    /// Called before the operations of a generation run.
+    fn on_generation_start(&mut self, _generation: usize, _population: &Population<Gene, S>) {}
+
+    /// Called after each operation, with its index in the operations.
+    fn on_operation(&mut self, _generation: usize, _operation_index: usize, _population: &Population<Gene, S>) {}
+
+    /// Called after all of the operations of a generation have run.
+    fn on_generation_end(&mut self, _generation: usize, _population: &Population<Gene, S>) {}
+
+    /// Called when the best score is better than any before it, before on_generation_end.
+    /// The score is the score provider's fitness, without the random offset.
+    fn on_new_best(&mut self, _generation: usize, _score: S, _agent: &Agent<Gene>) {}
+
+    /// Called by Manager when a child thread sends back its agents, before they are added to
+    /// the main population.
+    fn on_child_report(&mut self, _child_id: u64, _agents: &BTreeMap<AgentKey<S>, Agent<Gene>>) {}
+
+    /// Called by Manager after each migration between islands, with the stats of every island.
+    /// Islands run in their own threads, so the other methods apart from on_new_best aren't
+    /// called for them.
+    fn on_migration(&mut self, _generation: usize, _islands: &[IslandStats<S>]) {}
+}
+
+/// Observes nothing. Used by run_iterations.
+impl <Gene, S> Observer<Gene, S> for () {}
+
+/// Passes every call on to each observer in turn.
+impl <Gene, S> Observer<Gene, S> for Vec<Box<dyn Observer<Gene, S>>>
+where
+S: Score
+{
+    fn on_generation_start(&mut self, generation: usize, population: &Population<Gene, S>) {
+        for observer in self.iter_mut() {
+            observer.on_generation_start(generation, population);
+        }
+    }
+
+    fn on_operation(&mut self, generation: usize, operation_index: usize, population: &Population<Gene, S>) {
+        for observer in self.iter_mut() {
+            observer.on_operation(generation, operation_index, population);
+        }
+    }
+
+    fn on_generation_end(&mut self, generation: usize, population: &Population<Gene, S>) {
+        for observer in self.iter_mut() {
+            observer.on_generation_end(generation, population);
+        }
+    }
+
+    fn on_new_best(&mut self, generation: usize, score: S, agent: &Agent<Gene>) {
+        for observer in self.iter_mut() {
+            observer.on_new_best(generation, score, agent);
+        }
+    }
+
+    fn on_child_report(&mut self, child_id: u64, agents: &BTreeMap<AgentKey<S>, Agent<Gene>>) {
+        for observer in self.iter_mut() {
+            observer.on_child_report(child_id, agents);
+        }
+    }
+
+    fn on_migration(&mut self, generation: usize, islands: &[IslandStats<S>]) {
+        for observer in self.iter_mut() {
+            observer.on_migration(generation, islands);
+        }
+    }
+}
@@ -12,54 +12,113 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::agent::{Agent, crossover};
-use super::population::Population;
+use super::agent::Agent;
+use super::crossover::{CrossoverOperator, OnePointCrossover};
+use super::mutation::{MutationOperator, RemoveInsertMutation};
+use super::sampler::{GeneSampler, StandardSampler};
+use super::population::{Population, AgentKey};
 use std::hash::Hash;
 use rand::{
     distributions::{Distribution, Standard},
+    rngs::StdRng,
+    seq::index::sample,
     Rng,
 };
 use std::marker::{Send, PhantomData};
 use std::collections::BTreeMap;
-use super::fitness::{Score, ScoreProvider};
+use std::sync::Arc;
+use super::fitness::{Direction, Score, ScoreProvider};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum OperationType {
     Mutate,
     Crossover,
-    Cull
+    Cull,
+    /// Culls agents one at a time, asking the score provider to rank the population again after
+    /// each removal. With a ParetoScoreProvider and LowestScore selection, this is NSGA-II's
+    /// truncation, with crowding distances recalculated as agents are removed.
+    RankedCull
 }
 
-#[derive(Clone, Copy)]
+/// HighestScore and LowestScore follow the population's Direction, so when minimising,
+/// HighestScore selects the agents with the lowest (best) scores.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SelectionType {
     RandomAny,
     HighestScore,
-    LowestScore
+    LowestScore,
+    /// Selects the best of `size` randomly chosen agents, once for each agent selected.
+    /// Larger tournaments give more selective pressure. With replacement, an agent can win more
+    /// than one tournament, and is then selected once for each win.
+    /// When culling, the worst agent of each tournament is removed.
+    Tournament {
+        size: usize,
+        with_replacement: bool
+    },
+    /// Selects agents with a probability proportional to how much better their score is than
+    /// the worst score in the population, so zero and negative scores are handled.
+    /// Unlike StochasticUniversalSampling, agents are drawn without replacement, so each pick is
+    /// made from the agents not yet selected. Agents with the worst score have no weight, and are
+    /// only selected, at random, once every other agent has been.
+    /// When culling, agents are removed with a probability proportional to how much worse they are
+    /// than the best.
+    RouletteWheel,
+    /// Uses the same probabilities as RouletteWheel, but selects with evenly spaced pointers in a
+    /// single spin, so the agents selected closely follow their expected share. An agent can be
+    /// selected by more than one pointer, and is then selected once for each pointer.
+    StochasticUniversalSampling,
+    /// Selects agents with a probability that increases linearly with their rank.
+    /// The pressure, between 1.0 and 2.0, is how many times more likely the best agent is to be
+    /// selected than an average agent. The worst agent gets a share of 2.0 - pressure.
+    /// Like RouletteWheel, agents are drawn without replacement.
+    LinearRank {
+        pressure: f64
+    },
+    /// Selects agents with a probability of base ^ n, where n is the number of agents ranked above
+    /// them. The base should be between 0.0 and 1.0, with smaller values giving more pressure.
+    /// Like RouletteWheel, agents are drawn without replacement.
+    ExponentialRank {
+        base: f64
+    }
 }
 
 /// Allows definition of parameters for selecting some agents from a population.
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Selection {
     selection_type: SelectionType,
     proportion: f64,
-    preferred_minimum: usize
+    preferred_minimum: usize,
+    elites: usize
 }
 
 impl Selection {
+    /// Panics if a LinearRank pressure isn't between 1.0 and 2.0, or an ExponentialRank base is
+    /// negative.
     pub fn with_values(selection_type: SelectionType, proportion: f64, preferred_minimum: usize) -> Self {
+        check_selection_type(selection_type);
         Self {
             selection_type: selection_type,
             proportion: proportion,
-            preferred_minimum: preferred_minimum
+            preferred_minimum: preferred_minimum,
+            elites: 0
         }
     }
 
+    /// Panics if a LinearRank pressure isn't between 1.0 and 2.0, or an ExponentialRank base is
+    /// negative.
     pub fn new(selection_type: SelectionType, proportion: f64) -> Self {
+        check_selection_type(selection_type);
         Self {
             selection_type: selection_type,
             proportion: proportion,
-            preferred_minimum: 1
+            preferred_minimum: 1,
+            elites: 0
         }
     }
 
@@ -75,114 +134,280 @@ impl Selection {
         self.preferred_minimum
     }
 
-    pub fn agents <'a, Gene> (&self, population: &'a Population<Gene>) -> BTreeMap<Score, &'a Agent<Gene>>
+    /// Sets the number of best scored agents that culling with RandomAny will never remove.
+    pub fn set_elites(&mut self, elites: usize) {
+        self.elites = elites;
+    }
+
+    pub fn elites(&self) -> usize {
+        self.elites
+    }
+
+    /// Returns the selected agents. Selection types that can pick an agent more than once return
+    /// it once for each pick.
+    pub fn agents <'a, Gene, S, R> (&self, population: &'a Population<Gene, S>, rng: &mut R) -> Vec<(AgentKey<S>, &'a Agent<Gene>)>
     where
-    Gene: Clone
+    Gene: Clone,
+    S: Score,
+    R: Rng + ?Sized
     {
         match self.selection_type {
-            SelectionType::RandomAny => get_random_subset(population.get_agents(), self.proportion, self.preferred_minimum),
-            SelectionType::HighestScore => get_highest_scored_agents(population.get_agents(), self.proportion, self.preferred_minimum),
-            SelectionType::LowestScore => get_lowest_scored_agents(population.get_agents(), self.proportion, self.preferred_minimum)
+            SelectionType::RandomAny => get_random_subset(population.get_agents(), self.proportion, self.preferred_minimum, rng),
+            SelectionType::HighestScore => get_highest_scored_agents(population, self.proportion, self.preferred_minimum),
+            SelectionType::LowestScore => get_lowest_scored_agents(population, self.proportion, self.preferred_minimum),
+            SelectionType::Tournament { size, with_replacement } =>
+                get_tournament_winners(population, self.proportion, self.preferred_minimum, size, with_replacement, rng),
+            SelectionType::RouletteWheel |
+            SelectionType::StochasticUniversalSampling |
+            SelectionType::LinearRank { .. } |
+            SelectionType::ExponentialRank { .. } => {
+                let number = self.count(population);
+                get_weighted_keys(population, self.selection_type, number, false, rng).into_iter()
+                    .filter_map(|key| population.get(key).map(|agent| (key, agent)))
+                    .collect()
+            }
         }
     }
 
-    pub fn count <Gene> (&self, population: &Population<Gene>) -> usize {
+    pub fn count <Gene, S> (&self, population: &Population<Gene, S>) -> usize
+    where
+    S: Score
+    {
         rate_to_number(population.len(), self.proportion, self.preferred_minimum)
     }
 }
 
+/// Rank parameters outside these ranges would give agents negative chances of being selected.
+fn check_selection_type(selection_type: SelectionType) {
+    match selection_type {
+        SelectionType::LinearRank { pressure } =>
+            assert!((1.0..=2.0).contains(&pressure), "LinearRank pressure must be between 1.0 and 2.0, got {}", pressure),
+        SelectionType::ExponentialRank { base } =>
+            assert!(base >= 0.0, "ExponentialRank base must not be negative, got {}", base),
+        _ => ()
+    }
+}
+
 /// Modifies a selection of a population.
 #[derive(Clone)]
-pub struct Operation <Gene, Data>
+pub struct Operation <Gene, Data, S = u64>
 where
-Standard: Distribution<Gene>,
 Gene: Clone + Hash + Send + 'static,
-Data: Clone + Send + 'static
+Data: Clone + Send + 'static,
+S: Score
 {
     selection: Selection,
     operation_type: OperationType,
+    crossover_operator: Arc<dyn CrossoverOperator<Gene>>,
+    mutation_operator: Option<Arc<dyn MutationOperator<Gene>>>,
     gene: PhantomData<Gene>,
-    data: PhantomData<Data>
+    data: PhantomData<Data>,
+    score: PhantomData<S>
 }
 
-impl <Gene, Data> Operation <Gene, Data>
+impl <Gene, Data, S> Operation <Gene, Data, S>
 where
-Standard: Distribution<Gene>,
 Gene: Clone + Hash + Send + 'static,
-Data: Clone + Send + 'static
+Data: Clone + Send + 'static,
+S: Score
 {
     pub fn with_values(
         selection: Selection,
         operation_type: OperationType
-        ) -> Self {
-        Self {
-            selection: selection,
-            operation_type: operation_type,
-            gene: PhantomData,
-            data: PhantomData
-        }
+        ) -> Self
+    where
+    Standard: Distribution<Gene>
+    {
+        Self::with_sampler(operation_type, selection, StandardSampler)
     }
 
     pub fn new(
         operation_type: OperationType,
         selection: Selection
+    ) -> Self
+    where
+    Standard: Distribution<Gene>
+    {
+        Self::with_sampler(operation_type, selection, StandardSampler)
+    }
+
+    /// Creates an operation that gets new genes from the sampler, for genes that can't be drawn
+    /// from the Standard distribution. Only Mutate operations use the sampler.
+    pub fn with_sampler<G>(operation_type: OperationType, selection: Selection, sampler: G) -> Self
+    where
+    G: GeneSampler<Gene> + 'static
+    {
+        Self::build(
+            operation_type,
+            selection,
+            Arc::new(OnePointCrossover),
+            Some(Arc::new(RemoveInsertMutation::with_sampler(5, sampler)))
+        )
+    }
+
+    pub fn operation_type(&self) -> OperationType {
+        self.operation_type
+    }
+
+    pub fn selection(&self) -> Selection {
+        self.selection
+    }
+
+    /// Creates a Cull operation, which doesn't need to know how genes are created.
+    pub fn cull(selection: Selection) -> Self {
+        Self::build(OperationType::Cull, selection, Arc::new(OnePointCrossover), None)
+    }
+
+    /// Creates a RankedCull operation, which doesn't need to know how genes are created.
+    pub fn ranked_cull(selection: Selection) -> Self {
+        Self::build(OperationType::RankedCull, selection, Arc::new(OnePointCrossover), None)
+    }
+
+    /// Creates a Crossover operation that uses the given operator instead of one-point crossover.
+    /// This can be one of the operators in the crossover or real modules, a function from the
+    /// permutation module, a closure, or your own implementation of CrossoverOperator.
+    pub fn with_crossover_operator<C>(selection: Selection, crossover_operator: C) -> Self
+    where
+    C: CrossoverOperator<Gene> + 'static
+    {
+        Self::build(OperationType::Crossover, selection, Arc::new(crossover_operator), None)
+    }
+
+    /// Creates a Mutate operation that uses the given operator instead of five rounds of
+    /// RemoveInsertMutation. This can be one of the operators in the mutation or real modules,
+    /// with its own strength, a function from the permutation module, a closure, or your own
+    /// implementation of MutationOperator.
+    pub fn with_mutation_operator<M>(selection: Selection, mutation_operator: M) -> Self
+    where
+    M: MutationOperator<Gene> + 'static
+    {
+        Self::build(OperationType::Mutate, selection, Arc::new(OnePointCrossover), Some(Arc::new(mutation_operator)))
+    }
+
+    fn build(
+        operation_type: OperationType,
+        selection: Selection,
+        crossover_operator: Arc<dyn CrossoverOperator<Gene>>,
+        mutation_operator: Option<Arc<dyn MutationOperator<Gene>>>
     ) -> Self {
         Self {
             selection: selection,
             operation_type: operation_type,
+            crossover_operator: crossover_operator,
+            mutation_operator: mutation_operator,
             gene: PhantomData,
-            data: PhantomData
+            data: PhantomData,
+            score: PhantomData
         }
     }
 
-    pub fn run (&self, population: Population<Gene>, data: &Data, score_provider: &mut ScoreProvider<Gene, Data>) -> Population<Gene>
+    pub fn run (&self, population: Population<Gene, S>, data: &Data, score_provider: &mut dyn ScoreProvider<Gene, Data, S>, rng: &mut StdRng) -> Population<Gene, S>
     {
-        match self.operation_type {
-            OperationType::Mutate => mutate_agents(population, self.selection, data, score_provider),
-            OperationType::Crossover => crossover_agents(population, self.selection, data, score_provider),
-            OperationType::Cull => cull_agents(population, self.selection)
-        }
+        let mut population = match self.operation_type {
+            OperationType::Mutate => match &self.mutation_operator {
+                Some(mutation_operator) =>
+                    mutate_agents(population, self.selection, mutation_operator.as_ref(), data, score_provider, rng),
+                // Only operations of other types are created without a mutation operator.
+                None => population
+            },
+            OperationType::Crossover => crossover_agents(population, self.selection, self.crossover_operator.as_ref(), data, score_provider, rng),
+            OperationType::Cull => cull_agents(population, self.selection, rng),
+            OperationType::RankedCull => cull_ranked_agents(population, self.selection, data, score_provider, rng)
+        };
+        score_provider.rank_population(&mut population, data);
+        population
+    }
+}
+
+/// The settings of an operation that can be stored.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct OperationSettings {
+    operation_type: OperationType,
+    selection: Selection
+}
+
+/// Only the operation type and selection are stored, as operators can't be serialized.
+#[cfg(feature = "serde")]
+impl <Gene, Data, S> Serialize for Operation<Gene, Data, S>
+where
+Gene: Clone + Hash + Send + 'static,
+Data: Clone + Send + 'static,
+S: Score
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+    Ser: Serializer
+    {
+        OperationSettings {
+            operation_type: self.operation_type,
+            selection: self.selection
+        }.serialize(serializer)
+    }
+}
+
+/// Creates the operation with Operation::new, so it uses the default operators and draws new genes
+/// from the Standard distribution. For other genes, deserialize the operation type and selection
+/// and pass them to the constructor you need.
+#[cfg(feature = "serde")]
+impl <'de, Gene, Data, S> Deserialize<'de> for Operation<Gene, Data, S>
+where
+Standard: Distribution<Gene>,
+Gene: Clone + Hash + Send + 'static,
+Data: Clone + Send + 'static,
+S: Score
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+    D: Deserializer<'de>
+    {
+        let settings = OperationSettings::deserialize(deserializer)?;
+        Ok(Operation::new(settings.operation_type, settings.selection))
     }
 }
 
-fn mutate_agents<Gene, Data>(
-    mut population: Population<Gene>,
+fn mutate_agents<Gene, Data, S>(
+    mut population: Population<Gene, S>,
     selection: Selection,
+    mutation_operator: &dyn MutationOperator<Gene>,
     data: &Data,
-    score_provider: &mut ScoreProvider<Gene, Data>
-) -> Population<Gene>
+    score_provider: &mut dyn ScoreProvider<Gene, Data, S>,
+    rng: &mut StdRng
+) -> Population<Gene, S>
 where
-Standard: Distribution<Gene>,
 Gene: Clone + Hash + Send + 'static,
-Data: Clone + Send + 'static
+Data: Clone + Send + 'static,
+S: Score
 {
-    let children = get_mutated_agents(selection.agents(&population));
+    let children = get_mutated_agents(selection.agents(&population, rng), mutation_operator, rng);
     let children = score_provider.evaluate_scores(children, data).unwrap();
-    let mut rng = rand::thread_rng();
     for agent in children {
-        let score_index = score_provider.get_score(&agent, data, &mut rng).unwrap();
+        let score_index = score_provider.get_score(&agent, data, rng).unwrap();
         population.insert(score_index, agent);
     }
 
     population
 }
 
-fn crossover_agents<Gene, Data>(
-    mut population: Population<Gene>,
+fn crossover_agents<Gene, Data, S>(
+    mut population: Population<Gene, S>,
     selection: Selection,
+    crossover_operator: &dyn CrossoverOperator<Gene>,
     data: &Data,
-    score_provider: &mut ScoreProvider<Gene, Data>
-) -> Population<Gene>
+    score_provider: &mut dyn ScoreProvider<Gene, Data, S>,
+    rng: &mut StdRng
+) -> Population<Gene, S>
 where
-Standard: Distribution<Gene>,
 Gene: Clone + Hash + Send + 'static,
-Data: Clone + Send + 'static
+Data: Clone + Send + 'static,
+S: Score
 {
     let pairs = create_random_pairs(
-        selection.agents(&population)
+        selection.agents(&population, rng),
+        rng
     );
 
-    let children = create_children_from_crossover(pairs, data, score_provider);
+    let children = create_children_from_crossover(pairs, crossover_operator, data, score_provider, rng);
     for (score_index, agent) in children {
         population.insert(score_index, agent);
     }
@@ -190,153 +415,402 @@ Data: Clone + Send + 'static
     population
 }
 
-fn cull_agents<Gene>(
-    mut population: Population<Gene>,
+fn cull_agents<Gene, S, R>(
+    mut population: Population<Gene, S>,
     selection: Selection,
-) -> Population<Gene>
+    rng: &mut R
+) -> Population<Gene, S>
+where
+S: Score,
+R: Rng + ?Sized
 {
-    let keys: Vec<Score> = population.get_agents().keys().map(|k| *k).collect();
     let cull_number = selection.count(&population);
-    if cull_number >= keys.len() {
+    if cull_number >= population.len() {
         return population;
     }
     
     match selection.selection_type() {
-        SelectionType::LowestScore => population.cull_all_below(keys[cull_number]),
-        SelectionType::HighestScore => population.cull_all_above(keys[cull_number]),
-        SelectionType::RandomAny => panic!("RandomAny selection not yet implemented for cull agents")
+        SelectionType::LowestScore => population.cull_worst(cull_number),
+        SelectionType::HighestScore => population.cull_best(cull_number),
+        SelectionType::RandomAny => population.cull_random(cull_number, selection.elites(), rng),
+        SelectionType::Tournament { size, .. } => {
+            let mut keys = population.get_keys();
+            for _ in 0..cull_number {
+                if let Some(index) = hold_tournament(&keys, size, population.direction(), true, rng) {
+                    population.remove(keys.swap_remove(index));
+                }
+            }
+        },
+        selection_type @ SelectionType::RouletteWheel |
+        selection_type @ SelectionType::StochasticUniversalSampling |
+        selection_type @ SelectionType::LinearRank { .. } |
+        selection_type @ SelectionType::ExponentialRank { .. } => {
+            for key in get_weighted_keys(&population, selection_type, cull_number, true, rng) {
+                population.remove(key);
+            }
+        }
     };
     population
 }
 
-fn get_mutated_agents<Gene>(
-    agents: BTreeMap<Score, &Agent<Gene>>,
+fn cull_ranked_agents<Gene, Data, S>(
+    mut population: Population<Gene, S>,
+    selection: Selection,
+    data: &Data,
+    score_provider: &mut dyn ScoreProvider<Gene, Data, S>,
+    rng: &mut StdRng
+) -> Population<Gene, S>
+where
+S: Score
+{
+    let cull_number = selection.count(&population);
+    if cull_number >= population.len() {
+        return population;
+    }
+
+    for _ in 0..cull_number {
+        let key = match selection.selection_type() {
+            SelectionType::LowestScore => population.worst_key(),
+            SelectionType::HighestScore => population.best_key(),
+            SelectionType::RandomAny => {
+                let candidates = population.len().saturating_sub(selection.elites());
+                if candidates == 0 {
+                    None
+                } else {
+                    population.iter_worst_first().nth(rng.gen_range(0, candidates)).map(|(key, _)| *key)
+                }
+            },
+            SelectionType::Tournament { size, .. } => {
+                let keys = population.get_keys();
+                hold_tournament(&keys, size, population.direction(), true, rng).map(|index| keys[index])
+            },
+            selection_type @ SelectionType::RouletteWheel |
+            selection_type @ SelectionType::StochasticUniversalSampling |
+            selection_type @ SelectionType::LinearRank { .. } |
+            selection_type @ SelectionType::ExponentialRank { .. } =>
+                get_weighted_keys(&population, selection_type, 1, true, rng).pop()
+        };
+        if let Some(key) = key {
+            population.remove(key);
+        }
+        score_provider.rank_population(&mut population, data);
+    }
+    population
+}
+
+fn get_mutated_agents<Gene, S>(
+    agents: Vec<(AgentKey<S>, &Agent<Gene>)>,
+    mutation_operator: &dyn MutationOperator<Gene>,
+    rng: &mut StdRng
 ) -> Vec<Agent<Gene>>
-where Standard: Distribution<Gene>,
+where
 Gene: Clone + Hash + Send
 {
     let mut children = Vec::new();
-    for (_, mut agent) in agents {
-        let mut clone = agent.clone();
-        clone.mutate();
-        children.push(clone);
+    for (_, agent) in agents {
+        children.push(mutation_operator.mutate(agent, rng));
     }
     children
 }
 
-fn create_children_from_crossover<Gene, Data>(
+fn create_children_from_crossover<Gene, Data, S>(
     pairs: Vec<(Agent<Gene>, Agent<Gene>)>,
+    crossover_operator: &dyn CrossoverOperator<Gene>,
     data: &Data,
-    score_provider: &mut ScoreProvider<Gene, Data>,
-) -> Vec<(Score, Agent<Gene>)>
+    score_provider: &mut dyn ScoreProvider<Gene, Data, S>,
+    rng: &mut StdRng
+) -> Vec<(S, Agent<Gene>)>
 where
-Standard: Distribution<Gene>,
 Gene: Clone + Hash
 {
     let mut children = Vec::new();
 
     for (parent_one, parent_two) in pairs {
-        let child = crossover(&parent_one, &parent_two);
+        let child = crossover_operator.crossover(&parent_one, &parent_two, rng);
         children.push(child);
     }
     let children = score_provider.evaluate_scores(children, data).unwrap();
 
     let mut agents = Vec::new();
-    let mut rng = rand::thread_rng();
     for agent in children {
-        let score_index = score_provider.get_score(&agent, data, &mut rng).unwrap();
+        let score_index = score_provider.get_score(&agent, data, rng).unwrap();
         agents.push((score_index, agent));
     }
     return agents;
 }
 
-fn get_random_subset<Gene>(
-    agents: &BTreeMap<Score, Agent<Gene>>,
+fn get_random_subset<'a, Gene, S, R>(
+    agents: &'a BTreeMap<AgentKey<S>, Agent<Gene>>,
     rate: f64,
-    preferred_minimum: usize
-) -> BTreeMap<Score, &Agent<Gene>>
-where Gene: Clone
+    preferred_minimum: usize,
+    rng: &mut R
+) -> Vec<(AgentKey<S>, &'a Agent<Gene>)>
+where
+Gene: Clone,
+S: Score,
+R: Rng + ?Sized
 {
     let number = rate_to_number(agents.len(), rate, preferred_minimum);
-    let keys: Vec<Score> = agents.keys().map(|k| *k).collect();
-    let mut rng = rand::thread_rng();
-    let mut subset = BTreeMap::new();
-    for _ in 0..number {
-        let key = keys[rng.gen_range(0, keys.len())];
-        let agent = agents.get(&key);
-        if agent.is_some() {
-            subset.insert(key, agent.unwrap());
-        }
+    let entries: Vec<(&AgentKey<S>, &Agent<Gene>)> = agents.iter().collect();
+    let mut subset = Vec::new();
+    for index in sample(rng, entries.len(), number).into_iter() {
+        let (key, agent) = entries[index];
+        subset.push((*key, agent));
     }
 
     subset
 }
 
-fn get_highest_scored_agents<Gene>(
-    agents: &BTreeMap<Score, Agent<Gene>>,
+fn get_highest_scored_agents<Gene, S>(
+    population: &Population<Gene, S>,
     rate: f64,
     preferred_minimum: usize
-) -> BTreeMap<Score, &Agent<Gene>>
-where Gene: Clone
+) -> Vec<(AgentKey<S>, &Agent<Gene>)>
+where
+Gene: Clone,
+S: Score
 {
-    let number = rate_to_number(agents.len(), rate, preferred_minimum);
-    let mut keys: Vec<Score> = agents.keys().map(|k| *k).collect();
-    let keys_len = keys.len();
-    keys.drain(0..(keys_len - number));
-    let mut subset = BTreeMap::new();
-    for key in keys {
-        let agent = agents.get(&key);
-        if agent.is_some() {
-            subset.insert(key, agent.unwrap());
-        }
-    }
-
-    subset
+    let number = rate_to_number(population.len(), rate, preferred_minimum);
+    population.iter_best_first().take(number).map(|(key, agent)| (*key, agent)).collect()
 }
 
-fn get_lowest_scored_agents<Gene>(
-    agents: &BTreeMap<Score, Agent<Gene>>,
+fn get_lowest_scored_agents<Gene, S>(
+    population: &Population<Gene, S>,
     rate: f64,
     preferred_minimum: usize
-) -> BTreeMap<Score, &Agent<Gene>>
-where Gene: Clone
+) -> Vec<(AgentKey<S>, &Agent<Gene>)>
+where
+Gene: Clone,
+S: Score
 {
-    let number = rate_to_number(agents.len(), rate, preferred_minimum);
-    let mut keys: Vec<Score> = agents.keys().map(|k| *k).collect();
-    keys.truncate(number);
-    let mut subset = BTreeMap::new();
-    for key in keys {
-        let agent = agents.get(&key);
-        if agent.is_some() {
-            subset.insert(key, agent.unwrap());
+    let number = rate_to_number(population.len(), rate, preferred_minimum);
+    population.iter_worst_first().take(number).map(|(key, agent)| (*key, agent)).collect()
+}
+
+fn get_tournament_winners<'a, Gene, S, R>(
+    population: &'a Population<Gene, S>,
+    rate: f64,
+    preferred_minimum: usize,
+    size: usize,
+    with_replacement: bool,
+    rng: &mut R
+) -> Vec<(AgentKey<S>, &'a Agent<Gene>)>
+where
+Gene: Clone,
+S: Score,
+R: Rng + ?Sized
+{
+    let number = rate_to_number(population.len(), rate, preferred_minimum);
+    let mut keys = population.get_keys();
+    let mut winners = Vec::new();
+    for _ in 0..number {
+        let index = match hold_tournament(&keys, size, population.direction(), false, rng) {
+            Some(index) => index,
+            None => break
+        };
+        let key = if with_replacement {
+            keys[index]
+        } else {
+            keys.swap_remove(index)
+        };
+        if let Some(agent) = population.get(key) {
+            winners.push((key, agent));
         }
     }
 
-    subset
+    winners
+}
+
+/// Picks `size` of the keys at random and returns the index of the best scored,
+/// or the worst scored if `find_worst` is set. Returns None if there are no keys.
+fn hold_tournament<S, R>(
+    keys: &[AgentKey<S>],
+    size: usize,
+    direction: Direction,
+    find_worst: bool,
+    rng: &mut R
+) -> Option<usize>
+where
+S: Score,
+R: Rng + ?Sized
+{
+    if keys.is_empty() {
+        return None;
+    }
+    let size = size.clamp(1, keys.len());
+
+    let mut winner: Option<usize> = None;
+    for index in sample(rng, keys.len(), size).into_iter() {
+        let (score, _) = keys[index];
+        winner = match winner {
+            Some(current) if find_worst && !direction.is_better(keys[current].0, score) => Some(current),
+            Some(current) if !find_worst && !direction.is_better(score, keys[current].0) => Some(current),
+            _ => Some(index)
+        };
+    }
+    winner
+}
+
+/// Chooses keys for the fitness proportionate and rank based selection types. A key chosen more
+/// than once is returned once for each time, except when `prefer_worst` is set. Then the
+/// probabilities are reversed so that worse agents are more likely to be chosen, for culling,
+/// and duplicates are removed.
+fn get_weighted_keys<Gene, S, R>(
+    population: &Population<Gene, S>,
+    selection_type: SelectionType,
+    number: usize,
+    prefer_worst: bool,
+    rng: &mut R
+) -> Vec<AgentKey<S>>
+where
+S: Score,
+R: Rng + ?Sized
+{
+    // Ordered from the least likely to be chosen to the most likely.
+    let (keys, direction): (Vec<AgentKey<S>>, Direction) = if prefer_worst {
+        (population.iter_best_first().map(|(key, _)| *key).collect(), population.direction().reversed())
+    } else {
+        (population.iter_worst_first().map(|(key, _)| *key).collect(), population.direction())
+    };
+
+    let weights = match selection_type {
+        SelectionType::LinearRank { pressure } => linear_rank_weights(keys.len(), pressure),
+        SelectionType::ExponentialRank { base } => exponential_rank_weights(keys.len(), base),
+        _ => fitness_weights(&keys, direction)
+    };
+
+    let indices = match selection_type {
+        SelectionType::StochasticUniversalSampling => stochastic_universal_sample(&weights, number, rng),
+        _ => weighted_sample(weights, number, rng)
+    };
+
+    let mut chosen: Vec<AgentKey<S>> = indices.into_iter().map(|index| keys[index]).collect();
+    if prefer_worst {
+        chosen.sort();
+        chosen.dedup();
+    }
+    chosen
+}
+
+/// Weights each key by how much better its score is than the worst score. If no score is better
+/// than the worst, every key is weighted equally.
+fn fitness_weights<S>(keys: &[AgentKey<S>], direction: Direction) -> Vec<f64>
+where
+S: Score
+{
+    let values: Vec<f64> = keys.iter()
+        .map(|(score, _)| match direction {
+            Direction::Maximise => score.to_f64(),
+            Direction::Minimise => -score.to_f64()
+        })
+        .collect();
+    let worst = values.iter().cloned().filter(|value| value.is_finite()).fold(f64::INFINITY, f64::min);
+
+    let weights: Vec<f64> = values.iter()
+        .map(|value| value - worst)
+        .map(|weight| if weight.is_finite() && weight > 0.0 { weight } else { 0.0 })
+        .collect();
+
+    if weights.iter().sum::<f64>() > 0.0 {
+        weights
+    } else {
+        vec![1.0; keys.len()]
+    }
+}
+
+/// Weights for keys ordered from worst to best.
+fn linear_rank_weights(number: usize, pressure: f64) -> Vec<f64> {
+    if number < 2 {
+        return vec![1.0; number];
+    }
+    let pressure = if pressure.is_nan() { 1.0 } else { pressure.clamp(1.0, 2.0) };
+    (0..number)
+        .map(|rank| (2.0 - pressure) + 2.0 * (pressure - 1.0) * rank as f64 / (number - 1) as f64)
+        .collect()
+}
+
+/// Weights for keys ordered from worst to best. A negative base is treated as 0.0, so no weight
+/// is negative.
+fn exponential_rank_weights(number: usize, base: f64) -> Vec<f64> {
+    let base = base.max(0.0);
+    (0..number)
+        .map(|rank| base.powi((number - 1 - rank) as i32))
+        .collect()
+}
+
+/// Chooses up to `number` distinct indices, each with a probability proportional to its weight.
+/// Indices with no weight are only chosen once every weighted index has been chosen.
+fn weighted_sample<R>(mut weights: Vec<f64>, number: usize, rng: &mut R) -> Vec<usize>
+where
+R: Rng + ?Sized
+{
+    let mut chosen = Vec::new();
+    let mut remaining: Vec<usize> = (0..weights.len()).collect();
+    while chosen.len() < number && !remaining.is_empty() {
+        let total: f64 = remaining.iter().map(|index| weights[*index]).sum();
+        let position = if total > 0.0 && total.is_finite() {
+            let mut spin = rng.gen_range(0.0, total);
+            remaining.iter()
+                .position(|index| {
+                    spin -= weights[*index];
+                    spin < 0.0
+                })
+                .unwrap_or(remaining.len() - 1)
+        } else {
+            rng.gen_range(0, remaining.len())
+        };
+        let index = remaining.swap_remove(position);
+        weights[index] = 0.0;
+        chosen.push(index);
+    }
+    chosen
+}
+
+/// Chooses `number` indices using evenly spaced pointers over the weights.
+/// Indices are returned in order and can be chosen more than once.
+fn stochastic_universal_sample<R>(weights: &[f64], number: usize, rng: &mut R) -> Vec<usize>
+where
+R: Rng + ?Sized
+{
+    let total: f64 = weights.iter().sum();
+    if number == 0 || weights.is_empty() || total <= 0.0 || !total.is_finite() {
+        return sample(rng, weights.len(), number.min(weights.len())).into_vec();
+    }
+
+    let spacing = total / number as f64;
+    let mut pointer = rng.gen_range(0.0, spacing);
+    let mut cumulative = 0.0;
+    let mut chosen = Vec::new();
+    for (index, weight) in weights.iter().enumerate() {
+        cumulative += weight;
+        while pointer < cumulative && chosen.len() < number {
+            chosen.push(index);
+            pointer += spacing;
+        }
+    }
+    // Rounding can leave the last pointer just past the end.
+    while chosen.len() < number {
+        chosen.push(weights.len() - 1);
+    }
+    chosen
 }
 
-fn create_random_pairs<Gene>(
-    agents: BTreeMap<Score, &Agent<Gene>>,
+fn create_random_pairs<Gene, S>(
+    agents: Vec<(AgentKey<S>, &Agent<Gene>)>,
+    rng: &mut StdRng
 ) -> Vec<(Agent<Gene>, Agent<Gene>)> 
 where
-Gene: Clone
+Gene: Clone,
+S: Score
 {
-    let keys: Vec<&Score> = agents.keys().collect();
-    let mut rng = rand::thread_rng();
     let mut pairs = Vec::new();
-    let count = keys.len();
+    let count = agents.len();
     for _ in 0..count {
-        let one_key = keys[rng.gen_range(0, keys.len())];
-        let two_key = keys[rng.gen_range(0, keys.len())];
-
-        let one_agent = agents.get(one_key);
-        let two_agent = agents.get(two_key);
-        if one_agent.is_some() && two_agent.is_some() {
-            let one_agent = *one_agent.unwrap();
-            let two_agent = *two_agent.unwrap();
-            if !one_agent.has_same_genes(two_agent) {
-                pairs.push((one_agent.clone(), two_agent.clone()));
-            }
+        let (_, one_agent) = agents[rng.gen_range(0, count)];
+        let (_, two_agent) = agents[rng.gen_range(0, count)];
+
+        if !one_agent.has_same_genes(two_agent) {
+            pairs.push((one_agent.clone(), two_agent.clone()));
         }
     }
 
@@ -344,18 +818,19 @@ Gene: Clone
 }
 
 
-pub fn cull_lowest_agents<Gene>(
-    mut population: Population<Gene>,
+pub fn cull_lowest_agents<Gene, S>(
+    mut population: Population<Gene, S>,
     rate: f64,
     preferred_minimum: usize
-) -> Population<Gene>
+) -> Population<Gene, S>
+where
+S: Score
 {
-    let keys: Vec<Score> = population.get_agents().keys().map(|k| *k).collect();
-    let cull_number = rate_to_number(keys.len(), rate, preferred_minimum);
-    if cull_number >= keys.len() {
+    let cull_number = rate_to_number(population.len(), rate, preferred_minimum);
+    if cull_number >= population.len() {
         return population;
     }
-    population.cull_all_below(keys[cull_number]);
+    population.cull_worst(cull_number);
     population
 }
 
@@ -374,53 +849,314 @@ fn rate_to_number(population: usize, rate: f64, preferred_minimum: usize) -> usi
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::super::fitness::{GeneralScoreProvider, ScoreError};
+    use rand::SeedableRng;
+    use super::super::fitness::{Direction, GeneralScoreProvider, ScoreError};
 
-    fn get_score_index(agent: &Agent<u8>, _data: &u8) -> Result<Score, ScoreError> {
-        let score = agent.get_genes()[0] as Score;
+    fn get_score_index(agent: &Agent<u8>, _data: &u8) -> Result<u64, ScoreError> {
+        let score = agent.get_genes()[0] as u64;
         Ok(score)
     }
 
     #[test]
     fn selection_random_any_returns_correct_proportion() {
+        let mut rng = StdRng::seed_from_u64(0);
         let selection = Selection::with_values(SelectionType::RandomAny, 0.25, 0);
 
-        let population = Population::new(8, 1, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25));
+        let population = Population::new(8, 1, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
 
-        let agent_map = selection.agents(&population);
+        let agent_map = selection.agents(&population, &mut rng);
         assert_eq!(2, agent_map.len());
     }
 
     #[test]
     fn selection_highest_score_returns_highest() {
+        let mut rng = StdRng::seed_from_u64(0);
         let selection = Selection::with_values(SelectionType::HighestScore, 0.25, 0);
 
-        let population = Population::new(8, 1, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25));
+        let population = Population::new(8, 1, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
 
-        let agent_map = selection.agents(&population);
+        let agent_map = selection.agents(&population, &mut rng);
         assert_eq!(2, agent_map.len());
 
         let mut iter = population.get_agents().iter().rev();
-        let (score, _) = iter.next().unwrap();
-        assert!(agent_map.contains_key(score));
-        let (score, _) = iter.next().unwrap();
-        assert!(agent_map.contains_key(score));
+        let (key, _) = iter.next().unwrap();
+        assert!(agent_map.iter().any(|(selected, _)| selected == key));
+        let (key, _) = iter.next().unwrap();
+        assert!(agent_map.iter().any(|(selected, _)| selected == key));
     }
 
     #[test]
     fn selection_lowest_score_returns_lowest() {
+        let mut rng = StdRng::seed_from_u64(0);
         let selection = Selection::with_values(SelectionType::LowestScore, 0.25, 0);
 
-        let population = Population::new(8, 1, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25));
+        let population = Population::new(8, 1, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
 
-        let agent_map = selection.agents(&population);
+        let agent_map = selection.agents(&population, &mut rng);
         assert_eq!(2, agent_map.len());
 
         let mut iter = population.get_agents().iter();
-        let (score, _) = iter.next().unwrap();
-        assert!(agent_map.contains_key(score));
-        let (score, _) = iter.next().unwrap();
-        assert!(agent_map.contains_key(score));
+        let (key, _) = iter.next().unwrap();
+        assert!(agent_map.iter().any(|(selected, _)| selected == key));
+        let (key, _) = iter.next().unwrap();
+        assert!(agent_map.iter().any(|(selected, _)| selected == key));
+    }
+
+    #[test]
+    fn cull_lowest_score_keeps_agents_with_equal_scores() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(false);
+        for score in &[5, 5, 5, 7, 9] {
+            population.insert(*score, Agent::with_genes(1, &mut rng));
+        }
+
+        let selection = Selection::with_values(SelectionType::LowestScore, 0.4, 0);
+        let population = cull_agents(population, selection, &mut rng);
+        assert_eq!(vec![5, 7, 9], population.get_scores());
+    }
+
+    #[test]
+    fn cull_highest_score_removes_highest() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(false);
+        for score in &[5, 5, 5, 7, 9] {
+            population.insert(*score, Agent::with_genes(1, &mut rng));
+        }
+
+        let selection = Selection::with_values(SelectionType::HighestScore, 0.4, 0);
+        let population = cull_agents(population, selection, &mut rng);
+        assert_eq!(vec![5, 5, 5], population.get_scores());
+    }
+
+    #[test]
+    fn cull_lowest_score_when_minimising_removes_highest() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(false);
+        population.set_direction(Direction::Minimise);
+        for score in &[5, 5, 5, 7, 9] {
+            population.insert(*score, Agent::with_genes(1, &mut rng));
+        }
+
+        let selection = Selection::with_values(SelectionType::LowestScore, 0.4, 0);
+        let population = cull_agents(population, selection, &mut rng);
+        assert_eq!(vec![5, 5, 5], population.get_scores());
+    }
+
+    #[test]
+    fn cull_random_any_removes_correct_proportion() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let population = Population::new(8, 1, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
+
+        let selection = Selection::with_values(SelectionType::RandomAny, 0.25, 0);
+        let population = cull_agents(population, selection, &mut rng);
+        assert_eq!(6, population.len());
+    }
+
+    #[test]
+    fn cull_random_any_spares_elites() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(false);
+        population.set_direction(Direction::Minimise);
+        for score in 0..8 {
+            population.insert(score, Agent::with_genes(1, &mut rng));
+        }
+
+        let mut selection = Selection::with_values(SelectionType::RandomAny, 0.75, 0);
+        selection.set_elites(2);
+        let population = cull_agents(population, selection, &mut rng);
+        assert_eq!(vec![0, 1], population.get_scores());
+    }
+
+    #[test]
+    fn selection_highest_score_when_minimising_returns_lowest() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let selection = Selection::with_values(SelectionType::HighestScore, 0.25, 0);
+
+        let mut population: Population<u8> = Population::new_empty(false);
+        population.set_direction(Direction::Minimise);
+        for score in 0..8 {
+            population.insert(score, Agent::with_genes(1, &mut rng));
+        }
+
+        let agent_map = selection.agents(&population, &mut rng);
+        let scores: Vec<u64> = agent_map.iter().map(|((score, _), _)| *score).collect();
+        assert_eq!(vec![0, 1], scores);
+    }
+
+    #[test]
+    fn selection_tournament_of_whole_population_returns_highest() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let selection = Selection::with_values(SelectionType::Tournament { size: 8, with_replacement: false }, 0.25, 0);
+
+        let mut population: Population<u8> = Population::new_empty(false);
+        for score in 0..8 {
+            population.insert(score, Agent::with_genes(1, &mut rng));
+        }
+
+        let agent_map = selection.agents(&population, &mut rng);
+        let scores: Vec<u64> = agent_map.iter().map(|((score, _), _)| *score).collect();
+        assert_eq!(vec![7, 6], scores);
+    }
+
+    #[test]
+    fn selection_tournament_without_replacement_returns_correct_proportion() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let selection = Selection::with_values(SelectionType::Tournament { size: 3, with_replacement: false }, 0.5, 0);
+
+        let population = Population::new(8, 1, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
+
+        let agent_map = selection.agents(&population, &mut rng);
+        assert_eq!(4, agent_map.len());
+    }
+
+    #[test]
+    fn selection_tournament_with_replacement_can_pick_winner_again() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let selection = Selection::with_values(SelectionType::Tournament { size: 8, with_replacement: true }, 0.5, 0);
+
+        let mut population: Population<u8> = Population::new_empty(false);
+        for score in 0..8 {
+            population.insert(score, Agent::with_genes(1, &mut rng));
+        }
+
+        let agent_map = selection.agents(&population, &mut rng);
+        let scores: Vec<u64> = agent_map.iter().map(|((score, _), _)| *score).collect();
+        assert_eq!(vec![7, 7, 7, 7], scores);
+    }
+
+    #[test]
+    fn cull_tournament_removes_losers() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(false);
+        population.set_direction(Direction::Minimise);
+        for score in 0..8 {
+            population.insert(score, Agent::with_genes(1, &mut rng));
+        }
+
+        let selection = Selection::with_values(SelectionType::Tournament { size: 8, with_replacement: false }, 0.25, 0);
+        let population = cull_agents(population, selection, &mut rng);
+        assert_eq!(vec![0, 1, 2, 3, 4, 5], population.get_scores());
+    }
+
+    #[test]
+    fn selection_roulette_wheel_selects_worst_only_once_others_are_selected() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let selection = Selection::with_values(SelectionType::RouletteWheel, 0.0, 1);
+        let exhausting = Selection::with_values(SelectionType::RouletteWheel, 0.5, 1);
+
+        let mut population: Population<u8> = Population::new_empty(false);
+        for score in &[0, 0, 0, 10] {
+            population.insert(*score, Agent::with_genes(1, &mut rng));
+        }
+
+        for _ in 0..10 {
+            let agent_map = selection.agents(&population, &mut rng);
+            let scores: Vec<u64> = agent_map.iter().map(|((score, _), _)| *score).collect();
+            assert_eq!(vec![10], scores);
+
+            let agent_map = exhausting.agents(&population, &mut rng);
+            let scores: Vec<u64> = agent_map.iter().map(|((score, _), _)| *score).collect();
+            assert_eq!(vec![10, 0], scores);
+        }
+    }
+
+    #[test]
+    fn selection_roulette_wheel_handles_equal_zero_scores() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let selection = Selection::with_values(SelectionType::RouletteWheel, 0.5, 0);
+
+        let mut population: Population<u8> = Population::new_empty(false);
+        for _ in 0..8 {
+            population.insert(0, Agent::with_genes(1, &mut rng));
+        }
+
+        let agent_map = selection.agents(&population, &mut rng);
+        assert_eq!(4, agent_map.len());
+    }
+
+    #[test]
+    fn selection_roulette_wheel_when_minimising_selects_lowest() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let selection = Selection::with_values(SelectionType::RouletteWheel, 0.0, 1);
+
+        let mut population: Population<u8, i64> = Population::new_empty(false);
+        population.set_direction(Direction::Minimise);
+        for score in &[-5, 10, 10, 10] {
+            population.insert(*score, Agent::with_genes(1, &mut rng));
+        }
+
+        let agent_map = selection.agents(&population, &mut rng);
+        let scores: Vec<i64> = agent_map.iter().map(|((score, _), _)| *score).collect();
+        assert_eq!(vec![-5], scores);
+    }
+
+    #[test]
+    fn cull_roulette_wheel_removes_worse_agents() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(false);
+        for score in &[0, 10, 10, 10] {
+            population.insert(*score, Agent::with_genes(1, &mut rng));
+        }
+
+        let selection = Selection::with_values(SelectionType::RouletteWheel, 0.0, 1);
+        let population = cull_agents(population, selection, &mut rng);
+        assert_eq!(vec![10, 10, 10], population.get_scores());
+    }
+
+    #[test]
+    fn selection_rank_based_returns_correct_proportion() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let population = Population::new(8, 1, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
+
+        for selection_type in &[SelectionType::LinearRank { pressure: 1.5 }, SelectionType::ExponentialRank { base: 0.8 }] {
+            let selection = Selection::with_values(*selection_type, 0.5, 0);
+            assert_eq!(4, selection.agents(&population, &mut rng).len());
+        }
+    }
+
+    #[test]
+    fn selection_stochastic_universal_sampling_keeps_repeated_picks() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let selection = Selection::with_values(SelectionType::StochasticUniversalSampling, 0.5, 0);
+
+        let mut population: Population<u8> = Population::new_empty(false);
+        for score in &[0, 0, 0, 10] {
+            population.insert(*score, Agent::with_genes(1, &mut rng));
+        }
+
+        let agent_map = selection.agents(&population, &mut rng);
+        let scores: Vec<u64> = agent_map.iter().map(|((score, _), _)| *score).collect();
+        assert_eq!(vec![10, 10], scores);
+    }
+
+    #[test]
+    #[should_panic]
+    fn selection_rejects_linear_rank_pressure_out_of_range() {
+        Selection::new(SelectionType::LinearRank { pressure: 2.5 }, 0.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn selection_rejects_negative_exponential_rank_base() {
+        Selection::new(SelectionType::ExponentialRank { base: -0.5 }, 0.5);
+    }
+
+    #[test]
+    fn exponential_rank_weights_are_never_negative() {
+        assert_eq!(vec![0.0, 0.0, 1.0], exponential_rank_weights(3, -0.5));
+    }
+
+    #[test]
+    fn stochastic_universal_sample_follows_weights() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(vec![0, 1, 2, 2], stochastic_universal_sample(&[1.0, 1.0, 2.0], 4, &mut rng));
+        assert_eq!(vec![2, 2], stochastic_universal_sample(&[0.0, 0.0, 2.0], 2, &mut rng));
+    }
+
+    #[test]
+    fn linear_rank_weights_follow_pressure() {
+        assert_eq!(vec![0.0, 1.0, 2.0], linear_rank_weights(3, 2.0));
+        assert_eq!(vec![1.0, 1.0, 1.0], linear_rank_weights(3, 1.0));
     }
 
     #[test]
@@ -428,6 +1164,44 @@ mod tests {
         assert_eq!(16, rate_to_number(20, 0.8, 0));
     }
 
+    #[test]
+    fn mutate_with_operator_uses_operator() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut score_provider = GeneralScoreProvider::new(get_score_index, 0);
+        let population = Population::new(4, 1, false, &0, &mut score_provider, &mut rng);
+        let gene = 7;
+        let operation = Operation::with_mutation_operator(
+            Selection::with_values(SelectionType::RandomAny, 1.0, 0),
+            move |agent: &Agent<u8>, _rng: &mut StdRng| {
+                let mut child = agent.clone();
+                child.set_genes(vec![gene]);
+                child
+            });
+
+        let population = operation.run(population, &0, &mut score_provider, &mut rng);
+        assert_eq!(8, population.len());
+        assert_eq!(4, population.get_scores().iter().filter(|score| **score == 7).count());
+    }
+
+    #[test]
+    fn crossover_with_operator_uses_operator() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut score_provider = GeneralScoreProvider::new(get_score_index, 0);
+        let population = Population::new(4, 3, false, &0, &mut score_provider, &mut rng);
+        let operation = Operation::with_crossover_operator(
+            Selection::with_values(SelectionType::RandomAny, 1.0, 0),
+            |parent: &Agent<u8>, _other: &Agent<u8>, _rng: &mut StdRng| {
+                let mut child = parent.clone();
+                child.set_genes(vec![200, 0, 0]);
+                child
+            });
+
+        let population = operation.run(population, &0, &mut score_provider, &mut rng);
+        let children = population.get_scores().iter().filter(|score| **score == 200).count();
+        assert!(children > 0);
+        assert_eq!(4 + children, population.len());
+    }
+
     #[test]
     fn rate_to_number_population_is_zero() {
         assert_eq!(0, rate_to_number(0, 0.0, 0));
@@ -460,4 +1234,21 @@ mod tests {
     fn rate_to_number_minimum_preference_greater_than_population() {
         assert_eq!(4, rate_to_number(4, 0.5, 5));
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_keeps_operation_settings() {
+        let mut selection = Selection::with_values(SelectionType::Tournament { size: 3, with_replacement: false }, 0.3, 2);
+        selection.set_elites(1);
+        let operation: Operation<u8, u8> = Operation::new(OperationType::Cull, selection);
+        let json = ::serde_json::to_string(&operation).unwrap();
+
+        let loaded: Operation<u8, u8> = ::serde_json::from_str(&json).unwrap();
+        assert!(matches!(loaded.operation_type(), OperationType::Cull));
+        let selection = loaded.selection();
+        assert!(matches!(selection.selection_type(), SelectionType::Tournament { size: 3, with_replacement: false }));
+        assert_eq!(0.3, selection.proportion());
+        assert_eq!(2, selection.preferred_minimum());
+        assert_eq!(1, selection.elites());
+    }
+}
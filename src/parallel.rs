@@ -0,0 +1,351 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::agent::Agent;
+use super::fitness::{check_score, Fitness, FitnessFunction, Score, ScoreError, ScoreProvider};
+use rand::rngs::StdRng;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::panic;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// The result of scoring a set of genes, which is set once by whichever clone evaluates them
+/// first. Other clones that need it meanwhile wait for it to be set.
+type Slot<S> = Arc<OnceLock<Result<S, ScoreError>>>;
+
+/// Scores agents like GeneralScoreProvider, but evaluates each batch of new agents across a
+/// number of worker threads. Use it when the fitness function is slow compared to the rest of
+/// an iteration.
+/// Clones share the score cache, so the child threads or islands of a Manager don't evaluate
+/// genes that another has already scored. If a clone needs genes that another is still
+/// evaluating, it waits for that evaluation instead of making its own.
+#[derive(Clone)]
+pub struct ParallelScoreProvider <Gene, Data, S = u64, F = FitnessFunction<Gene, Data, S>>
+where
+Gene: Clone + Hash,
+S: Score,
+F: Fitness<Gene, Data, S>
+{
+    scoring_function: F,
+    offset: S,
+    threads: usize,
+    score_cache: Arc<Mutex<HashMap<u64, Slot<S>>>>,
+    evaluations: u64,
+    gene: PhantomData<Gene>,
+    data: PhantomData<Data>
+}
+
+impl <Gene, Data, S, F> ParallelScoreProvider <Gene, Data, S, F>
+where
+Gene: Clone + Hash,
+S: Score,
+F: Fitness<Gene, Data, S>
+{
+    /// Uses a worker thread for each CPU. The offset works as it does for GeneralScoreProvider.
+    pub fn new(scoring_function: F, offset: S) -> Self {
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        Self {
+            scoring_function,
+            offset,
+            threads,
+            score_cache: Arc::new(Mutex::new(HashMap::new())),
+            evaluations: 0,
+            gene: PhantomData,
+            data: PhantomData
+        }
+    }
+
+    /// Sets the number of worker threads for each batch. With 1, agents are evaluated on the
+    /// calling thread.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// The number of genes that have been scored, by this provider or any of its clones.
+    pub fn cached_scores(&self) -> usize {
+        self.score_cache.lock().unwrap().values()
+            .filter(|slot| matches!(slot.get(), Some(Ok(_))))
+            .count()
+    }
+
+    fn cached_score(&self, hash: u64) -> Option<S> {
+        self.score_cache.lock().unwrap().get(&hash)
+            .and_then(|slot| slot.get())
+            .and_then(|score| score.as_ref().ok().cloned())
+    }
+
+    /// Returns the slot for the genes with the hash, adding an empty one if they haven't been
+    /// scored or taken by a clone.
+    fn slot(&self, hash: u64) -> Slot<S> {
+        self.score_cache.lock().unwrap().entry(hash).or_default().clone()
+    }
+
+    /// Returns the agent's score from the slot, evaluating it if no clone has yet, and whether
+    /// this call evaluated it. Failed evaluations are taken out of the cache, so they are tried
+    /// again next time.
+    fn score(&self, slot: &Slot<S>, agent: &Agent<Gene>, data: &Data) -> (Result<S, ScoreError>, bool) {
+        let mut evaluated = false;
+        let score = slot.get_or_init(|| {
+            evaluated = true;
+            check_score(self.scoring_function.score(agent, data))
+        }).clone();
+        if evaluated && score.is_err() {
+            let mut cache = self.score_cache.lock().unwrap();
+            if cache.get(&agent.get_hash()).is_some_and(|cached| Arc::ptr_eq(cached, slot)) {
+                cache.remove(&agent.get_hash());
+            }
+        }
+        (score, evaluated)
+    }
+
+    /// Scores the agents across the worker threads, returning their scores in the same order and
+    /// the number of evaluations made. Each worker takes the next agent that hasn't been taken, so
+    /// slow agents don't hold up the others.
+    fn score_all(&self, agents: &[(&Agent<Gene>, Slot<S>)], data: &Data) -> (Vec<Result<S, ScoreError>>, u64)
+    where
+    Gene: Sync,
+    Data: Sync
+    {
+        let threads = self.threads.min(agents.len());
+        if threads <= 1 {
+            let scored: Vec<(Result<S, ScoreError>, bool)> = agents.iter()
+                .map(|(agent, slot)| self.score(slot, agent, data))
+                .collect();
+            let evaluations = scored.iter().filter(|(_, evaluated)| *evaluated).count() as u64;
+            return (scored.into_iter().map(|(score, _)| score).collect(), evaluations);
+        }
+
+        let next = AtomicUsize::new(0);
+        let mut scores: Vec<Option<Result<S, ScoreError>>> = agents.iter().map(|_| None).collect();
+        let mut evaluations = 0;
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| scope.spawn(|| {
+                    let mut scored = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= agents.len() {
+                            return scored;
+                        }
+                        let (agent, slot) = &agents[index];
+                        scored.push((index, self.score(slot, agent, data)));
+                    }
+                }))
+                .collect();
+            for worker in workers {
+                for (index, (score, evaluated)) in worker.join().unwrap_or_else(|error| panic::resume_unwind(error)) {
+                    scores[index] = Some(score);
+                    if evaluated {
+                        evaluations += 1;
+                    }
+                }
+            }
+        });
+        (scores.into_iter().map(|score| score.expect("every agent is scored by a worker")).collect(), evaluations)
+    }
+}
+
+impl <Gene, Data, S, F> ScoreProvider<Gene, Data, S> for ParallelScoreProvider <Gene, Data, S, F>
+where
+Gene: Clone + Hash + Sync,
+Data: Sync,
+S: Score,
+F: Fitness<Gene, Data, S>
+{
+    /// Evaluates the agents whose genes haven't been scored before, once for each distinct set of
+    /// genes. Agents that the fitness function returns an error or NaN for are left out.
+    fn evaluate_scores(&mut self, agents: Vec<Agent<Gene>>, data: &Data) -> Result<Vec<Agent<Gene>>, ScoreError> {
+        let mut pending = Vec::new();
+        let mut seen = HashSet::new();
+        for agent in &agents {
+            if seen.insert(agent.get_hash()) {
+                pending.push((agent, self.slot(agent.get_hash())));
+            }
+        }
+
+        let (scores, evaluations) = self.score_all(&pending, data);
+        self.evaluations += evaluations;
+
+        let scored: HashSet<u64> = pending.iter().zip(scores)
+            .filter(|(_, score)| score.is_ok())
+            .map(|((agent, _), _)| agent.get_hash())
+            .collect();
+        Ok(agents.into_iter().filter(|agent| scored.contains(&agent.get_hash())).collect())
+    }
+
+    fn get_score(&mut self, agent: &Agent<Gene>, data: &Data, rng: &mut StdRng) -> Result<S, ScoreError> {
+        let offset = self.offset.saturating_add(self.offset).random_below(rng);
+
+        let (score, evaluated) = self.score(&self.slot(agent.get_hash()), agent, data);
+        if evaluated {
+            self.evaluations += 1;
+        }
+
+        Ok(score?.saturating_add(offset).saturating_sub(self.offset))
+    }
+
+    /// The number of evaluations made by this provider, not counting those of its clones.
+    fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+
+    fn fitness(&self, agent: &Agent<Gene>) -> Option<S> {
+        self.cached_score(agent.get_hash())
+    }
+
+    fn add_fitness(&mut self, agent: &Agent<Gene>, fitness: S) {
+        let _ = self.slot(agent.get_hash()).set(Ok(fitness));
+    }
+
+    fn cached_fitness(&self) -> Vec<(u64, S)> {
+        let mut scores: Vec<(u64, S)> = self.score_cache.lock().unwrap().iter()
+            .filter_map(|(hash, slot)| match slot.get() {
+                Some(Ok(score)) => Some((*hash, *score)),
+                _ => None
+            })
+            .collect();
+        scores.sort_by_key(|(hash, _)| *hash);
+        scores
+    }
+
+    fn restore_fitness(&mut self, scores: Vec<(u64, S)>) {
+        for (hash, score) in scores {
+            let _ = self.slot(hash).set(Ok(score));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use super::super::manager::{create_manager, Manager};
+    use std::sync::atomic::AtomicU64;
+    use std::time::Duration;
+
+    fn get_score_index(agent: &Agent<u8>, _data: &u8) -> Result<u64, ScoreError> {
+        Ok(agent.get_genes().iter().map(|gene| *gene as u64).sum())
+    }
+
+    #[test]
+    fn evaluates_each_distinct_agent_once() {
+        let calls = Arc::new(AtomicU64::new(0));
+        let counter = calls.clone();
+        let fitness = move |agent: &Agent<u8>, _data: &u8| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(agent.get_genes()[0] as u64)
+        };
+        let mut score_provider = ParallelScoreProvider::new(fitness, 0);
+        score_provider.set_threads(4);
+
+        let agents: Vec<Agent<u8>> = (0..40).map(|gene| Agent::from_genes(vec![gene % 20])).collect();
+        let scored = score_provider.evaluate_scores(agents, &0).unwrap();
+        assert_eq!(40, scored.len());
+        assert_eq!(20, calls.load(Ordering::SeqCst));
+        assert_eq!(20, score_provider.evaluations());
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for agent in &scored {
+            assert_eq!(agent.get_genes()[0] as u64, score_provider.get_score(agent, &0, &mut rng).unwrap());
+        }
+        assert_eq!(20, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn clones_share_the_cache() {
+        let mut score_provider = ParallelScoreProvider::new(get_score_index, 0);
+        score_provider.set_threads(2);
+        let mut clone = score_provider.clone();
+
+        score_provider.evaluate_scores(vec![Agent::from_genes(vec![1, 2]), Agent::from_genes(vec![3])], &0).unwrap();
+        clone.evaluate_scores(vec![Agent::from_genes(vec![1, 2]), Agent::from_genes(vec![4])], &0).unwrap();
+        assert_eq!(3, clone.cached_scores());
+        assert_eq!(2, score_provider.evaluations());
+        assert_eq!(1, clone.evaluations());
+    }
+
+    #[test]
+    fn clones_wait_for_genes_another_is_evaluating() {
+        let calls = Arc::new(AtomicU64::new(0));
+        let counter = calls.clone();
+        let fitness = move |agent: &Agent<u8>, _data: &u8| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            Ok(agent.get_genes()[0] as u64)
+        };
+        let mut score_provider = ParallelScoreProvider::new(fitness, 0);
+        score_provider.set_threads(2);
+        let agents: Vec<Agent<u8>> = (0..8).map(|gene| Agent::from_genes(vec![gene])).collect();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let mut clone = score_provider.clone();
+                let agents = agents.clone();
+                thread::spawn(move || {
+                    let scored = clone.evaluate_scores(agents, &0).unwrap();
+                    (scored.len(), clone.evaluations())
+                })
+            })
+            .collect();
+        let results: Vec<(usize, u64)> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+
+        assert!(results.iter().all(|(scored, _)| *scored == 8));
+        assert_eq!(8, results.iter().map(|(_, evaluations)| evaluations).sum::<u64>());
+        assert_eq!(8, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn failed_evaluations_are_tried_again() {
+        let calls = Arc::new(AtomicU64::new(0));
+        let counter = calls.clone();
+        let fitness = move |_agent: &Agent<u8>, _data: &u8| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Err(ScoreError::new("can't be scored"))
+        };
+        let mut score_provider: ParallelScoreProvider<u8, u8, u64, _> = ParallelScoreProvider::new(fitness, 0);
+
+        let agents = vec![Agent::from_genes(vec![1])];
+        assert!(score_provider.evaluate_scores(agents.clone(), &0).unwrap().is_empty());
+        assert!(score_provider.evaluate_scores(agents, &0).unwrap().is_empty());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        assert_eq!(0, score_provider.cached_scores());
+    }
+
+    fn run_seeded<SP>(mut manager: Manager<u8, u8, SP>) -> Vec<u64>
+    where
+    SP: Clone + Send + ScoreProvider<u8, u8>
+    {
+        manager.set_number_of_genes(4, true);
+        manager.set_initial_population_size(10);
+        manager.set_max_population_size(50);
+        manager.set_iterations_per_cycle(5);
+        manager.set_seed(5);
+        manager.run(1000);
+        manager.get_population().get_scores()
+    }
+
+    #[test]
+    fn manager_gives_same_population_as_general_score_provider() {
+        let mut score_provider = ParallelScoreProvider::new(get_score_index, 25);
+        score_provider.set_threads(3);
+        assert_eq!(run_seeded(create_manager(get_score_index, 0)), run_seeded(Manager::new(score_provider, 0)));
+    }
+}
@@ -0,0 +1,424 @@
+// Copyright 2019 Brendan Cox
+// 
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::agent::Agent;
+use super::fitness::{Direction, Fitness, FloatScore, Score, ScoreError, ScoreProvider};
+use super::population::{AgentKey, Population};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use rand::{
+    rngs::StdRng,
+    Rng,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A fitness function returning a value for each objective.
+pub type ObjectivesFunction<Gene, Data> = fn(&Agent<Gene>, &Data) -> Result<Vec<f64>, ScoreError>;
+
+/// The rank of an agent within its population.
+/// Agents on an earlier front are better, and within a front, agents with a larger crowding
+/// distance are better. Greater ranks are better, so populations should use Direction::Maximise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParetoScore {
+    front: u64,
+    crowding: FloatScore
+}
+
+impl ParetoScore {
+    pub fn new(front: u64, crowding: f64) -> Self {
+        Self {
+            front,
+            crowding: FloatScore(crowding)
+        }
+    }
+
+    /// The non-dominated front, where zero is the Pareto front.
+    pub fn front(self) -> u64 {
+        self.front
+    }
+
+    pub fn crowding(self) -> f64 {
+        self.crowding.value()
+    }
+}
+
+impl PartialOrd for ParetoScore {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ParetoScore {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.front.cmp(&self.front)
+            .then(self.crowding.cmp(&other.crowding))
+    }
+}
+
+/// A rank can't be offset, so these leave the rank unchanged.
+impl Score for ParetoScore {
+    fn default_offset() -> Self {
+        ParetoScore::new(0, 0.0)
+    }
+
+    fn saturating_add(self, _other: Self) -> Self {
+        self
+    }
+
+    fn saturating_sub(self, _other: Self) -> Self {
+        self
+    }
+
+    fn random_below<R: Rng + ?Sized>(self, _rng: &mut R) -> Self {
+        self
+    }
+
+    /// Only the front is used, as crowding distances are only comparable within a front.
+    fn to_f64(self) -> f64 {
+        -(self.front as f64)
+    }
+}
+
+/// Returns true if the first objectives are at least as good as the second in every
+/// objective and strictly better in at least one.
+pub fn dominates(first: &[f64], second: &[f64], direction: Direction) -> bool {
+    let mut strictly_better = false;
+    for (one, two) in first.iter().zip(second.iter()) {
+        let one = FloatScore(*one);
+        let two = FloatScore(*two);
+        if direction.is_better(two, one) {
+            return false;
+        }
+        if direction.is_better(one, two) {
+            strictly_better = true;
+        }
+    }
+    strictly_better
+}
+
+/// Sorts the objectives into non-dominated fronts, returning the indices on each front.
+/// The first front is the Pareto front.
+pub fn non_dominated_sort(objectives: &[Vec<f64>], direction: Direction) -> Vec<Vec<usize>> {
+    let mut dominated_by: Vec<Vec<usize>> = vec![Vec::new(); objectives.len()];
+    let mut domination_count = vec![0; objectives.len()];
+    let mut fronts = Vec::new();
+    let mut current = Vec::new();
+
+    for one in 0..objectives.len() {
+        for two in (one + 1)..objectives.len() {
+            if dominates(&objectives[one], &objectives[two], direction) {
+                dominated_by[one].push(two);
+                domination_count[two] += 1;
+            } else if dominates(&objectives[two], &objectives[one], direction) {
+                dominated_by[two].push(one);
+                domination_count[one] += 1;
+            }
+        }
+    }
+
+    for (index, count) in domination_count.iter().enumerate() {
+        if *count == 0 {
+            current.push(index);
+        }
+    }
+
+    while !current.is_empty() {
+        let mut next = Vec::new();
+        for index in current.iter() {
+            for dominated in dominated_by[*index].iter() {
+                domination_count[*dominated] -= 1;
+                if domination_count[*dominated] == 0 {
+                    next.push(*dominated);
+                }
+            }
+        }
+        fronts.push(current);
+        current = next;
+    }
+
+    fronts
+}
+
+/// Returns the crowding distance of each member of the front, in the same order as the front.
+/// Members at either end of any objective have an infinite distance.
+pub fn crowding_distances(objectives: &[Vec<f64>], front: &[usize]) -> Vec<f64> {
+    let mut distances = vec![0.0; front.len()];
+    if front.len() <= 2 {
+        return vec![f64::INFINITY; front.len()];
+    }
+
+    let number_of_objectives = objectives[front[0]].len();
+    for objective in 0..number_of_objectives {
+        let values: Vec<f64> = front.iter().map(|index| objectives[*index].get(objective).cloned().unwrap_or(f64::NAN)).collect();
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|one, two| values[*one].total_cmp(&values[*two]));
+
+        let first = order[0];
+        let last = order[order.len() - 1];
+        distances[first] = f64::INFINITY;
+        distances[last] = f64::INFINITY;
+
+        let range = values[last] - values[first];
+        if range <= 0.0 || !range.is_finite() {
+            continue;
+        }
+        for position in order.windows(3) {
+            distances[position[1]] += (values[position[2]] - values[position[0]]) / range;
+        }
+    }
+
+    distances
+}
+
+/// Returns the agents on the Pareto front of a population ranked by a ParetoScoreProvider.
+pub fn pareto_front<Gene>(population: &Population<Gene, ParetoScore>) -> Vec<(&AgentKey<ParetoScore>, &Agent<Gene>)> {
+    population.iter_best_first()
+        .take_while(|((score, _), _)| score.front() == 0)
+        .collect()
+}
+
+/// Scores agents by their Pareto rank within the population, using a fitness function that
+/// returns a value for each objective. This is the ranking used by NSGA-II, so the usual
+/// operations select and cull by non-dominated front and then crowding distance.
+/// Scores given to new agents are provisional, based on the fronts found the last time the
+/// population was ranked, until the population is ranked again.
+#[derive(Clone)]
+pub struct ParetoScoreProvider <Gene, Data, F = ObjectivesFunction<Gene, Data>>
+where
+Gene: Clone + Hash,
+F: Fitness<Gene, Data, Vec<f64>>
+{
+    objectives_function: F,
+    direction: Direction,
+    objectives_cache: HashMap<u64, Vec<f64>>,
+    evaluations: u64,
+    fronts: Vec<Vec<Vec<f64>>>,
+    gene: PhantomData<Gene>,
+    data: PhantomData<Data>
+}
+
+impl <Gene, Data, F> ParetoScoreProvider <Gene, Data, F>
+where
+Gene: Clone + Hash,
+F: Fitness<Gene, Data, Vec<f64>>
+{
+    /// The direction applies to every objective. Negate an objective to optimise it the other way.
+    pub fn new(objectives_function: F, direction: Direction) -> Self {
+        Self {
+            objectives_function,
+            direction,
+            objectives_cache: HashMap::new(),
+            evaluations: 0,
+            fronts: Vec::new(),
+            gene: PhantomData,
+            data: PhantomData
+        }
+    }
+
+    /// Returns the objectives of an agent that has been scored.
+    pub fn objectives(&self, agent: &Agent<Gene>) -> Option<&Vec<f64>> {
+        self.objectives_cache.get(&agent.get_hash())
+    }
+
+    fn evaluate(&mut self, agent: &Agent<Gene>, data: &Data) -> Result<Vec<f64>, ScoreError> {
+        let hash = agent.get_hash();
+        if let Some(objectives) = self.objectives_cache.get(&hash) {
+            return Ok(objectives.clone());
+        }
+
+        self.evaluations += 1;
+        let objectives = self.objectives_function.score(agent, data)?;
+        self.objectives_cache.insert(hash, objectives.clone());
+        Ok(objectives)
+    }
+}
+
+impl <Gene, Data, F> ScoreProvider<Gene, Data, ParetoScore> for ParetoScoreProvider <Gene, Data, F>
+where
+Gene: Clone + Hash,
+F: Fitness<Gene, Data, Vec<f64>>
+{
+    fn evaluate_scores(&mut self, agents: Vec<Agent<Gene>>, data: &Data) -> Result<Vec<Agent<Gene>>, ScoreError> {
+        let mut cached = Vec::new();
+
+        for agent in agents {
+            // Agents that can't be scored are skipped.
+            if self.evaluate(&agent, data).is_ok() {
+                cached.push(agent);
+            }
+        }
+
+        Ok(cached)
+    }
+
+    fn get_score(&mut self, agent: &Agent<Gene>, data: &Data, _rng: &mut StdRng) -> Result<ParetoScore, ScoreError> {
+        let objectives = self.evaluate(agent, data)?;
+        let direction = self.direction;
+
+        let front = self.fronts.iter()
+            .position(|front| !front.iter().any(|other| dominates(other, &objectives, direction)))
+            .unwrap_or(self.fronts.len());
+
+        Ok(ParetoScore::new(front as u64, 0.0))
+    }
+
+    fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+
+    fn rank_population(&mut self, population: &mut Population<Gene, ParetoScore>, data: &Data) {
+        let mut ids = Vec::new();
+        let mut objectives = Vec::new();
+        let mut failed = Vec::new();
+        for (key, agent) in population.get_agents().iter() {
+            // Agents from child threads or other islands may not have been scored by this
+            // provider. Those that can't be scored are dropped.
+            match self.evaluate(agent, data) {
+                Ok(agent_objectives) => {
+                    ids.push(key.1);
+                    objectives.push(agent_objectives);
+                },
+                Err(_) => failed.push(*key)
+            }
+        }
+        for key in failed {
+            population.remove(key);
+        }
+
+        let fronts = non_dominated_sort(&objectives, self.direction);
+        let mut scores = HashMap::new();
+        for (front_index, front) in fronts.iter().enumerate() {
+            let distances = crowding_distances(&objectives, front);
+            for (index, distance) in front.iter().zip(distances) {
+                scores.insert(ids[*index], ParetoScore::new(front_index as u64, distance));
+            }
+        }
+
+        self.fronts = fronts.iter()
+            .map(|front| front.iter().map(|index| objectives[*index].clone()).collect())
+            .collect();
+        population.rescore(|(_, id), _| scores[id]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::evolution::run_iterations;
+    use super::super::operations::{Operation, OperationType, Selection, SelectionType};
+    use rand::SeedableRng;
+
+    fn get_objectives(agent: &Agent<u8>, _data: &u8) -> Result<Vec<f64>, ScoreError> {
+        let genes = agent.get_genes();
+        Ok(vec![genes[0] as f64, 255.0 - genes[0] as f64 + genes[1] as f64])
+    }
+
+    #[test]
+    fn dominates_requires_one_strictly_better_objective() {
+        assert!(dominates(&[2.0, 2.0], &[1.0, 2.0], Direction::Maximise));
+        assert!(!dominates(&[2.0, 2.0], &[2.0, 2.0], Direction::Maximise));
+        assert!(!dominates(&[2.0, 1.0], &[1.0, 2.0], Direction::Maximise));
+        assert!(dominates(&[1.0, 2.0], &[2.0, 2.0], Direction::Minimise));
+    }
+
+    #[test]
+    fn non_dominated_sort_finds_fronts() {
+        let objectives = vec![
+            vec![1.0, 1.0],
+            vec![3.0, 1.0],
+            vec![1.0, 3.0],
+            vec![2.0, 2.0],
+            vec![0.0, 0.0]
+        ];
+
+        let fronts = non_dominated_sort(&objectives, Direction::Maximise);
+        assert_eq!(vec![vec![1, 2, 3], vec![0], vec![4]], fronts);
+    }
+
+    #[test]
+    fn crowding_distances_favour_the_ends() {
+        let objectives = vec![
+            vec![0.0, 4.0],
+            vec![1.0, 3.0],
+            vec![3.0, 1.0],
+            vec![4.0, 0.0]
+        ];
+
+        let distances = crowding_distances(&objectives, &[0, 1, 2, 3]);
+        assert_eq!(f64::INFINITY, distances[0]);
+        assert_eq!(1.5, distances[1]);
+        assert_eq!(1.5, distances[2]);
+        assert_eq!(f64::INFINITY, distances[3]);
+    }
+
+    #[test]
+    fn pareto_score_orders_by_front_then_crowding() {
+        assert!(ParetoScore::new(0, 0.5) > ParetoScore::new(1, f64::INFINITY));
+        assert!(ParetoScore::new(1, 2.0) > ParetoScore::new(1, 0.5));
+    }
+
+    #[test]
+    fn rank_population_scores_by_front() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut score_provider = ParetoScoreProvider::new(get_objectives, Direction::Maximise);
+        let population = Population::new(20, 2, false, &0, &mut score_provider, &mut rng);
+
+        for ((score, _), agent) in population.get_agents().iter() {
+            let objectives = score_provider.objectives(agent).unwrap();
+            let dominated = population.get_agents().values()
+                .any(|other| dominates(score_provider.objectives(other).unwrap(), objectives, Direction::Maximise));
+            assert_eq!(!dominated, score.front() == 0);
+        }
+    }
+
+    fn get_objectives_of_nonzero(agent: &Agent<u8>, data: &u8) -> Result<Vec<f64>, ScoreError> {
+        if agent.get_genes()[0] == 0 {
+            return Err(ScoreError::new("the first gene can't be zero"));
+        }
+        get_objectives(agent, data)
+    }
+
+    #[test]
+    fn rank_population_drops_agents_that_fail() {
+        let mut score_provider = ParetoScoreProvider::new(get_objectives_of_nonzero, Direction::Maximise);
+        let mut population = Population::new_empty(false);
+        population.insert(ParetoScore::new(0, 0.0), Agent::from_genes(vec![0, 5]));
+        population.insert(ParetoScore::new(0, 0.0), Agent::from_genes(vec![3, 5]));
+        population.insert(ParetoScore::new(0, 0.0), Agent::from_genes(vec![4, 1]));
+        score_provider.rank_population(&mut population, &0);
+
+        assert_eq!(2, population.len());
+        assert!(population.get_agents().values().all(|agent| agent.get_genes()[0] != 0));
+    }
+
+    #[test]
+    fn ranked_cull_keeps_the_pareto_front() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut score_provider = ParetoScoreProvider::new(get_objectives, Direction::Maximise);
+        let population = Population::new(30, 2, false, &0, &mut score_provider, &mut rng);
+        let front_size = pareto_front(&population).len();
+
+        let operations = vec![
+            Operation::new(OperationType::RankedCull, Selection::with_values(SelectionType::LowestScore, 0.0, 30 - front_size))
+        ];
+        let population = run_iterations(population, 1, &0, &operations, &mut score_provider, &mut rng);
+
+        assert_eq!(front_size, population.len());
+        assert_eq!(front_size, pareto_front(&population).len());
+    }
+}
@@ -0,0 +1,434 @@
+// Copyright 2019 Brendan Cox
+// 
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::agent::Agent;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use rand::{
+    seq::SliceRandom,
+    Rng,
+};
+
+/// Creates an agent with the genes of the alphabet in a random order.
+/// The crossover and mutation functions in this module keep agents as orderings of their
+/// alphabet, which should not contain any gene twice. Crossover of parents that aren't orderings
+/// of the same genes returns a copy of the first parent.
+pub fn random_permutation<Gene, R>(alphabet: &[Gene], rng: &mut R) -> Agent<Gene>
+where
+Gene: Clone + Hash,
+R: Rng + ?Sized
+{
+    let mut genes = alphabet.to_vec();
+    genes.shuffle(rng);
+
+    let mut agent = Agent::new();
+    agent.set_genes(genes);
+    agent
+}
+
+/// Returns true if both sets of genes contain the same genes, in any order.
+pub fn is_permutation_of<Gene>(genes: &[Gene], other: &[Gene]) -> bool
+where
+Gene: Hash + Eq
+{
+    if genes.len() != other.len() {
+        return false;
+    }
+    let mut counts: HashMap<&Gene, isize> = HashMap::new();
+    for gene in genes {
+        *counts.entry(gene).or_insert(0) += 1;
+    }
+    for gene in other {
+        *counts.entry(gene).or_insert(0) -= 1;
+    }
+    counts.values().all(|count| *count == 0)
+}
+
+/// PMX. Copies a random section of the first parent, then fills the rest of the child from the
+/// second parent, following the mapping between the parents' sections to avoid duplicates.
+pub fn partially_mapped_crossover<Gene, R>(parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut R) -> Agent<Gene>
+where
+Gene: Clone + Hash + Eq,
+R: Rng + ?Sized
+{
+    let (genes1, genes2) = match compatible_genes(parent1, parent2) {
+        Some(genes) => genes,
+        None => return parent1.clone()
+    };
+    let (start, end) = get_section(genes1.len(), rng);
+    let positions2 = get_positions(genes2);
+
+    let mut child: Vec<Option<Gene>> = vec![None; genes1.len()];
+    for index in start..end {
+        child[index] = Some(genes1[index].clone());
+    }
+    let section: HashSet<&Gene> = genes1[start..end].iter().collect();
+
+    for (index, gene) in genes2.iter().enumerate().take(end).skip(start) {
+        if section.contains(gene) {
+            continue;
+        }
+        let mut position = index;
+        while position >= start && position < end {
+            position = positions2[&genes1[position]];
+        }
+        child[position] = Some(gene.clone());
+    }
+
+    let genes = child.into_iter()
+        .zip(genes2.iter())
+        .map(|(gene, other)| gene.unwrap_or_else(|| other.clone()))
+        .collect();
+    create_child(parent1, genes)
+}
+
+/// OX. Copies a random section of the first parent, then fills the rest of the child with the
+/// remaining genes in the order they appear in the second parent, starting after the section.
+pub fn order_crossover<Gene, R>(parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut R) -> Agent<Gene>
+where
+Gene: Clone + Hash + Eq,
+R: Rng + ?Sized
+{
+    let (genes1, genes2) = match compatible_genes(parent1, parent2) {
+        Some(genes) => genes,
+        None => return parent1.clone()
+    };
+    let length = genes1.len();
+    let (start, end) = get_section(length, rng);
+
+    let mut child: Vec<Option<Gene>> = vec![None; length];
+    for index in start..end {
+        child[index] = Some(genes1[index].clone());
+    }
+    let section: HashSet<&Gene> = genes1[start..end].iter().collect();
+
+    let mut remaining = (0..length)
+        .map(|offset| &genes2[(end + offset) % length])
+        .filter(|gene| !section.contains(gene));
+    for offset in 0..(length - (end - start)) {
+        child[(end + offset) % length] = remaining.next().cloned();
+    }
+
+    create_child(parent1, child.into_iter().map(|gene| gene.unwrap()).collect())
+}
+
+/// CX. Splits the positions into cycles between the parents, and takes alternate cycles from
+/// each parent, so every gene keeps the position it had in one of the parents.
+pub fn cycle_crossover<Gene, R>(parent1: &Agent<Gene>, parent2: &Agent<Gene>, _rng: &mut R) -> Agent<Gene>
+where
+Gene: Clone + Hash + Eq,
+R: Rng + ?Sized
+{
+    let (genes1, genes2) = match compatible_genes(parent1, parent2) {
+        Some(genes) => genes,
+        None => return parent1.clone()
+    };
+    let positions1 = get_positions(genes1);
+
+    let mut child: Vec<Option<Gene>> = vec![None; genes1.len()];
+    let mut from_first = true;
+    for start in 0..genes1.len() {
+        if child[start].is_some() {
+            continue;
+        }
+        let mut position = start;
+        loop {
+            child[position] = Some(if from_first { genes1[position].clone() } else { genes2[position].clone() });
+            position = positions1[&genes2[position]];
+            if position == start {
+                break;
+            }
+        }
+        from_first = !from_first;
+    }
+
+    create_child(parent1, child.into_iter().map(|gene| gene.unwrap()).collect())
+}
+
+/// ERX. Builds the child from the edges of both parents, treating each as a cycle, always moving
+/// to the neighbouring gene with the fewest remaining neighbours.
+pub fn edge_recombination_crossover<Gene, R>(parent1: &Agent<Gene>, parent2: &Agent<Gene>, rng: &mut R) -> Agent<Gene>
+where
+Gene: Clone + Hash + Eq,
+R: Rng + ?Sized
+{
+    let (genes1, genes2) = match compatible_genes(parent1, parent2) {
+        Some(genes) => genes,
+        None => return parent1.clone()
+    };
+    let length = genes1.len();
+    let positions1 = get_positions(genes1);
+
+    // Genes are referred to by their position in the first parent.
+    let mut neighbours: Vec<HashSet<usize>> = vec![HashSet::new(); length];
+    for genes in &[genes1, genes2] {
+        for index in 0..length {
+            let gene = positions1[&genes[index]];
+            neighbours[gene].insert(positions1[&genes[(index + 1) % length]]);
+            neighbours[gene].insert(positions1[&genes[(index + length - 1) % length]]);
+        }
+    }
+
+    let mut unused: Vec<usize> = (0..length).collect();
+    let mut current = if rng.gen() { 0 } else { positions1[&genes2[0]] };
+    let mut child = Vec::with_capacity(length);
+    loop {
+        child.push(genes1[current].clone());
+        unused.retain(|gene| *gene != current);
+        for list in neighbours.iter_mut() {
+            list.remove(&current);
+        }
+        if unused.is_empty() {
+            break;
+        }
+
+        let mut candidates: Vec<usize> = neighbours[current].iter().cloned().collect();
+        if candidates.is_empty() {
+            current = unused[rng.gen_range(0, unused.len())];
+            continue;
+        }
+        candidates.sort();
+        let fewest = candidates.iter().map(|gene| neighbours[*gene].len()).min().unwrap();
+        candidates.retain(|gene| neighbours[*gene].len() == fewest);
+        current = candidates[rng.gen_range(0, candidates.len())];
+    }
+
+    create_child(parent1, child)
+}
+
+/// Swaps two random genes.
+pub fn swap_mutation<Gene, R>(agent: &Agent<Gene>, rng: &mut R) -> Agent<Gene>
+where
+Gene: Clone + Hash,
+R: Rng + ?Sized
+{
+    let mut genes = agent.get_genes().clone();
+    if genes.len() < 2 {
+        return agent.clone();
+    }
+    swap_genes(&mut genes, rng);
+    create_child(agent, genes)
+}
+
+/// Moves a random gene to a random position.
+pub fn insertion_mutation<Gene, R>(agent: &Agent<Gene>, rng: &mut R) -> Agent<Gene>
+where
+Gene: Clone + Hash,
+R: Rng + ?Sized
+{
+    let mut genes = agent.get_genes().clone();
+    if genes.len() < 2 {
+        return agent.clone();
+    }
+    move_gene(&mut genes, rng);
+    create_child(agent, genes)
+}
+
+/// Reverses the order of a random section of genes.
+pub fn inversion_mutation<Gene, R>(agent: &Agent<Gene>, rng: &mut R) -> Agent<Gene>
+where
+Gene: Clone + Hash,
+R: Rng + ?Sized
+{
+    let mut genes = agent.get_genes().clone();
+    if genes.len() < 2 {
+        return agent.clone();
+    }
+    invert_section(&mut genes, rng);
+    create_child(agent, genes)
+}
+
+/// Shuffles a random section of genes.
+pub fn scramble_mutation<Gene, R>(agent: &Agent<Gene>, rng: &mut R) -> Agent<Gene>
+where
+Gene: Clone + Hash,
+R: Rng + ?Sized
+{
+    let mut genes = agent.get_genes().clone();
+    if genes.len() < 2 {
+        return agent.clone();
+    }
+    let (start, end) = get_section(genes.len(), rng);
+    genes[start..end].shuffle(rng);
+    create_child(agent, genes)
+}
+
+/// Swaps two random genes, which should not be empty.
+pub(crate) fn swap_genes<Gene, R>(genes: &mut [Gene], rng: &mut R)
+where
+R: Rng + ?Sized
+{
+    let first = rng.gen_range(0, genes.len());
+    let second = rng.gen_range(0, genes.len());
+    genes.swap(first, second);
+}
+
+/// Moves a random gene to a random position. The genes should not be empty.
+pub(crate) fn move_gene<Gene, R>(genes: &mut Vec<Gene>, rng: &mut R)
+where
+R: Rng + ?Sized
+{
+    let gene = genes.remove(rng.gen_range(0, genes.len()));
+    let position = rng.gen_range(0, genes.len() + 1);
+    genes.insert(position, gene);
+}
+
+/// Reverses the order of a random section of genes, which should not be empty.
+pub(crate) fn invert_section<Gene, R>(genes: &mut [Gene], rng: &mut R)
+where
+R: Rng + ?Sized
+{
+    let (start, end) = get_section(genes.len(), rng);
+    genes[start..end].reverse();
+}
+
+/// Returns the parents' genes if crossover can produce a valid permutation from them.
+/// Parents that contain a gene more than once aren't orderings of an alphabet, and the position
+/// lookups of the crossovers would loop forever on them.
+fn compatible_genes<'a, Gene>(parent1: &'a Agent<Gene>, parent2: &'a Agent<Gene>) -> Option<(&'a Vec<Gene>, &'a Vec<Gene>)>
+where
+Gene: Hash + Eq
+{
+    let genes1 = parent1.get_genes();
+    let genes2 = parent2.get_genes();
+    if genes1.len() < 2 || !is_permutation_of(genes1, genes2) || get_positions(genes1).len() != genes1.len() {
+        return None;
+    }
+    Some((genes1, genes2))
+}
+
+/// Returns a random range of at least one gene, as a start and an exclusive end.
+fn get_section<R>(length: usize, rng: &mut R) -> (usize, usize)
+where
+R: Rng + ?Sized
+{
+    let start = rng.gen_range(0, length);
+    let end = rng.gen_range(start + 1, length + 1);
+    (start, end)
+}
+
+fn get_positions<Gene>(genes: &[Gene]) -> HashMap<&Gene, usize>
+where
+Gene: Hash + Eq
+{
+    genes.iter().enumerate().map(|(index, gene)| (gene, index)).collect()
+}
+
+fn create_child<Gene>(parent: &Agent<Gene>, genes: Vec<Gene>) -> Agent<Gene>
+where
+Gene: Clone + Hash
+{
+    let mut child = parent.clone();
+    child.set_genes(genes);
+    child
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    type CrossoverFunction = fn(&Agent<u8>, &Agent<u8>, &mut StdRng) -> Agent<u8>;
+    type MutationFunction = fn(&Agent<u8>, &mut StdRng) -> Agent<u8>;
+
+    fn alphabet() -> Vec<u8> {
+        (0..20).collect()
+    }
+
+    #[test]
+    fn random_permutation_uses_whole_alphabet() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let agent = random_permutation(&alphabet(), &mut rng);
+        assert!(is_permutation_of(agent.get_genes(), &alphabet()));
+        assert_ne!(&alphabet(), agent.get_genes());
+    }
+
+    #[test]
+    fn crossovers_produce_permutations() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let crossovers: Vec<CrossoverFunction> = vec![
+            partially_mapped_crossover,
+            order_crossover,
+            cycle_crossover,
+            edge_recombination_crossover
+        ];
+
+        for crossover in crossovers {
+            for _ in 0..50 {
+                let parent1 = random_permutation(&alphabet(), &mut rng);
+                let parent2 = random_permutation(&alphabet(), &mut rng);
+                let child = crossover(&parent1, &parent2, &mut rng);
+                assert!(is_permutation_of(child.get_genes(), &alphabet()));
+            }
+        }
+    }
+
+    #[test]
+    fn mutations_produce_permutations() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mutations: Vec<MutationFunction> = vec![
+            swap_mutation,
+            insertion_mutation,
+            inversion_mutation,
+            scramble_mutation
+        ];
+
+        for mutation in mutations {
+            for _ in 0..50 {
+                let agent = random_permutation(&alphabet(), &mut rng);
+                let child = mutation(&agent, &mut rng);
+                assert!(is_permutation_of(child.get_genes(), &alphabet()));
+            }
+        }
+    }
+
+    #[test]
+    fn cycle_crossover_keeps_positions_from_a_parent() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let parent1 = random_permutation(&alphabet(), &mut rng);
+        let parent2 = random_permutation(&alphabet(), &mut rng);
+        let child = cycle_crossover(&parent1, &parent2, &mut rng);
+
+        for (index, gene) in child.get_genes().iter().enumerate() {
+            assert!(*gene == parent1.get_genes()[index] || *gene == parent2.get_genes()[index]);
+        }
+    }
+
+    #[test]
+    fn crossover_of_different_genes_returns_first_parent() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let parent1 = random_permutation(&alphabet(), &mut rng);
+        let parent2 = random_permutation(&[1, 2, 3], &mut rng);
+        let child = order_crossover(&parent1, &parent2, &mut rng);
+        assert_eq!(parent1.get_genes(), child.get_genes());
+    }
+
+    #[test]
+    fn crossover_of_repeated_genes_returns_first_parent() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let parent1 = Agent::from_genes(vec![1, 1, 2]);
+        let parent2 = Agent::from_genes(vec![2, 1, 1]);
+        let crossovers: Vec<CrossoverFunction> = vec![
+            partially_mapped_crossover,
+            order_crossover,
+            cycle_crossover,
+            edge_recombination_crossover
+        ];
+
+        for crossover in crossovers {
+            let child = crossover(&parent1, &parent2, &mut rng);
+            assert_eq!(parent1.get_genes(), child.get_genes());
+        }
+    }
+}
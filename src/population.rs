@@ -13,105 +13,184 @@
 // limitations under the License.
 
 use super::agent::Agent;
-use super::fitness::{Score, ScoreProvider};
+use super::fitness::{Direction, Score, ScoreProvider};
+use super::sampler::{GeneSampler, StandardSampler};
 use std::collections::{BTreeMap, HashSet};
 use std::hash::Hash;
 use rand::{
     distributions::{Distribution, Standard},
+    rngs::StdRng,
+    seq::index::sample,
     Rng,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Identifies an agent within a population.
+/// Agents are ordered by their score first and then by the order in which they were
+/// inserted, so agents that share a score are all retained.
+pub type AgentKey<S = u64> = (S, u64);
 
 #[derive(Clone)]
-pub struct Population <Gene> {
-    agents: BTreeMap<Score, Agent<Gene>>,
+pub struct Population <Gene, S = u64> {
+    agents: BTreeMap<AgentKey<S>, Agent<Gene>>,
     register: HashSet<u64>,
     unique_agents: bool,
-
+    max_size: Option<usize>,
+    direction: Direction,
+    next_id: u64
 }
 
-impl <Gene> Population <Gene> {
+impl <Gene, S> Population <Gene, S>
+where
+S: Score
+{
 
     pub fn new_empty(unique: bool) -> Self {
         Self {
             agents: BTreeMap::new(),
             register: HashSet::new(),
-            unique_agents: unique
+            unique_agents: unique,
+            max_size: None,
+            direction: Direction::Maximise,
+            next_id: 0
         }
     }
 
+    /// Creates a population of agents with random genes from the Standard distribution.
     pub fn new<Data, SP>(
         start_size: usize,
         number_of_genes: usize,
         unique: bool,
         data: &Data,
         score_provider: &mut SP,
-    ) -> Population<Gene> 
+        rng: &mut StdRng
+    ) -> Population<Gene, S> 
     where
     Standard: Distribution<Gene>,
     Gene: Hash + Clone,
-    SP: ScoreProvider<Gene, Data>
+    SP: ScoreProvider<Gene, Data, S>
+    {
+        Population::with_sampler(start_size, number_of_genes, unique, &StandardSampler, data, score_provider, rng)
+    }
+
+    /// Creates a population of agents with genes from the sampler.
+    pub fn with_sampler<Data, SP, G>(
+        start_size: usize,
+        number_of_genes: usize,
+        unique: bool,
+        sampler: &G,
+        data: &Data,
+        score_provider: &mut SP,
+        rng: &mut StdRng
+    ) -> Population<Gene, S>
+    where
+    Gene: Hash + Clone,
+    SP: ScoreProvider<Gene, Data, S>,
+    G: GeneSampler<Gene> + ?Sized
     {
         let mut population = Population::new_empty(unique);
-        let mut rng = rand::thread_rng();
-        let mut agents = Vec::new();
-        for _ in 0..start_size {
-            let agent = Agent::with_genes(number_of_genes);
-            if population.will_accept(&agent) {
-                agents.push(agent);
-            }
-        }
+        let agents = (0..start_size).map(|_| Agent::with_sampled_genes(number_of_genes, sampler, rng)).collect();
+        population.add_agents(agents, data, score_provider, rng);
 
-        let agents = score_provider.evaluate_scores(agents, &data).unwrap();
+        population
+    }
 
-        for agent in agents {
-            let mut score = score_provider.get_score(&agent, &data, &mut rng).unwrap();
+    /// Scores the agents with the score provider and inserts them, then ranks the population.
+    /// Agents that the population won't accept are left out.
+    pub fn add_agents<Data, SP>(
+        &mut self,
+        agents: Vec<Agent<Gene>>,
+        data: &Data,
+        score_provider: &mut SP,
+        rng: &mut StdRng
+    )
+    where
+    Gene: Hash + Clone,
+    SP: ScoreProvider<Gene, Data, S>
+    {
+        let agents = agents.into_iter().filter(|agent| self.will_accept(agent)).collect();
+        let agents = score_provider.evaluate_scores(agents, data).unwrap();
 
-            loop {
-                if score == 0 {
-                    break;
-                }
-                if population.contains_score(score) {
-                    score -= 1;
-                } else {
-                    break;
-                }
-            }
+        for agent in agents {
+            let score = score_provider.get_score(&agent, data, rng).unwrap();
+            self.insert(score, agent);
+        }
+        score_provider.rank_population(self, data);
+    }
 
-            population.insert(score, agent);
+    pub fn set_agents(&mut self, agents: BTreeMap<AgentKey<S>, Agent<Gene>>) {
+        for ((score, _), agent) in agents {
+            self.insert(score, agent);
         }
+    }
 
-        population
+    /// Sets whether higher or lower scores are better. Defaults to Direction::Maximise.
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
     }
 
-    pub fn set_agents(&mut self, agents: BTreeMap<Score, Agent<Gene>>) {
-        for (score, agent) in agents {
-            self.insert(score, agent);
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Limits the number of agents the population will hold.
+    /// Once full, an inserted agent replaces the worst scored agent if it has a better score.
+    pub fn set_max_size(&mut self, max_size: Option<usize>) {
+        self.max_size = max_size;
+        if let Some(max_size) = max_size {
+            if self.agents.len() > max_size {
+                let excess = self.agents.len() - max_size;
+                self.cull_worst(excess);
+            }
         }
     }
 
-    pub fn insert(&mut self, score: Score, agent: Agent<Gene>) {
-        if self.unique_agents {
-            if self.register.contains(&agent.get_hash()) {
-                return;
+    pub fn max_size(&self) -> Option<usize> {
+        self.max_size
+    }
+
+    /// Adds an agent with the given score, returning the key it was stored under.
+    /// Returns None if the population only allows unique agents and already has this one,
+    /// or if the population is full and the agent doesn't score better than its worst agent.
+    pub fn insert(&mut self, score: S, agent: Agent<Gene>) -> Option<AgentKey<S>> {
+        if !self.will_accept(&agent) {
+            return None;
+        }
+        if let Some(max_size) = self.max_size {
+            if self.agents.len() >= max_size {
+                match self.worst_key() {
+                    Some(worst) if self.direction.is_better(score, worst.0) => {
+                        self.remove(worst);
+                    },
+                    _ => return None
+                }
             }
+        }
+        if self.unique_agents {
             self.register.insert(agent.get_hash());
         }
-        self.agents.insert(score, agent);
+        let key = (score, self.next_id);
+        self.next_id += 1;
+        self.agents.insert(key, agent);
+        Some(key)
     }
 
-    pub fn remove(&mut self, score: Score) -> Option<Agent<Gene>> where Gene: Clone {
-        let agent = self.agents.remove(&score);
-        if self.unique_agents && agent.is_some() {
-            self.register.remove(&agent.clone().unwrap().get_hash());
+    pub fn remove(&mut self, key: AgentKey<S>) -> Option<Agent<Gene>> {
+        let agent = self.agents.remove(&key);
+        if self.unique_agents {
+            if let Some(ref agent) = agent {
+                self.register.remove(&agent.get_hash());
+            }
         }
         agent
     }
 
-    pub fn get(&self, score: Score) -> Option<&Agent<Gene>> {
-        self.agents.get(&score)
+    pub fn get(&self, key: AgentKey<S>) -> Option<&Agent<Gene>> {
+        self.agents.get(&key)
     }
 
-    pub fn get_agents(&self) -> &BTreeMap<Score, Agent<Gene>> {
+    pub fn get_agents(&self) -> &BTreeMap<AgentKey<S>, Agent<Gene>> {
         &self.agents
     }
 
@@ -119,28 +198,118 @@ impl <Gene> Population <Gene> {
         self.agents.len()
     }
 
-    pub fn cull_all_below(&mut self, score: Score) {
-        self.agents = self.agents.split_off(&score);
-        if self.unique_agents {
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    /// Removes all agents ordered before the given key.
+    pub fn cull_all_below(&mut self, key: AgentKey<S>) {
+        self.agents = self.agents.split_off(&key);
+        self.rebuild_register();
+    }
+
+    /// Removes all agents ordered after the given key.
+    pub fn cull_all_above(&mut self, key: AgentKey<S>) {
+        let mut above = self.agents.split_off(&key);
+        if let Some(agent) = above.remove(&key) {
+            self.agents.insert(key, agent);
+        }
+        self.rebuild_register();
+    }
+
+    /// Removes the given number of agents, starting from the worst scored.
+    pub fn cull_worst(&mut self, count: usize) {
+        let keys = self.get_keys();
+        if count >= keys.len() {
+            self.agents.clear();
             self.register.clear();
-            for (_, agent) in &self.agents {
-                self.register.insert(agent.get_hash());
-            }
+            return;
+        }
+        if count == 0 {
+            return;
+        }
+        match self.direction {
+            Direction::Maximise => self.cull_all_below(keys[count]),
+            Direction::Minimise => self.cull_all_above(keys[keys.len() - count - 1])
         }
     }
 
-    pub fn cull_all_above(&mut self, score: Score) {
-        self.agents.split_off(&score);
-        if self.unique_agents {
+    /// Removes the given number of agents, starting from the best scored.
+    pub fn cull_best(&mut self, count: usize) {
+        let keys = self.get_keys();
+        if count >= keys.len() {
+            self.agents.clear();
             self.register.clear();
-            for (_, agent) in &self.agents {
-                self.register.insert(agent.get_hash());
-            }
+            return;
+        }
+        if count == 0 {
+            return;
+        }
+        match self.direction {
+            Direction::Maximise => self.cull_all_above(keys[keys.len() - count - 1]),
+            Direction::Minimise => self.cull_all_below(keys[count])
+        }
+    }
+
+    /// Removes the given number of randomly chosen agents.
+    /// The given number of elites, the best scored agents, are never removed.
+    pub fn cull_random<R: Rng + ?Sized>(&mut self, count: usize, elites: usize, rng: &mut R) {
+        let candidates: Vec<AgentKey<S>> = self.iter_worst_first()
+            .take(self.len().saturating_sub(elites))
+            .map(|(key, _)| *key)
+            .collect();
+        let count = count.min(candidates.len());
+        for index in sample(rng, candidates.len(), count).into_iter() {
+            self.remove(candidates[index]);
+        }
+    }
+
+    pub fn best_key(&self) -> Option<AgentKey<S>> {
+        self.iter_best_first().next().map(|(key, _)| *key)
+    }
+
+    pub fn worst_key(&self) -> Option<AgentKey<S>> {
+        self.iter_worst_first().next().map(|(key, _)| *key)
+    }
+
+    /// Returns the best agent by the score provider's fitness, which leaves out the random offset
+    /// of the population's scores, along with its fitness. Agents the score provider has no
+    /// fitness for are compared by their score in the population.
+    pub fn best_fitness<Data, SP>(&self, score_provider: &SP) -> Option<(S, &Agent<Gene>)>
+    where
+    SP: ScoreProvider<Gene, Data, S> + ?Sized
+    {
+        let direction = self.direction;
+        self.iter_best_first()
+            .map(|((score, _), agent)| (score_provider.fitness(agent).unwrap_or(*score), agent))
+            .fold(None, |best, (fitness, agent)| match best {
+                Some((best_fitness, _)) if !direction.is_better(fitness, best_fitness) => best,
+                _ => Some((fitness, agent))
+            })
+    }
+
+    /// Iterates over the agents from best scored to worst scored.
+    pub fn iter_best_first(&self) -> Box<dyn Iterator<Item = (&AgentKey<S>, &Agent<Gene>)> + '_> {
+        match self.direction {
+            Direction::Maximise => Box::new(self.agents.iter().rev()),
+            Direction::Minimise => Box::new(self.agents.iter())
+        }
+    }
+
+    /// Iterates over the agents from worst scored to best scored.
+    pub fn iter_worst_first(&self) -> Box<dyn Iterator<Item = (&AgentKey<S>, &Agent<Gene>)> + '_> {
+        match self.direction {
+            Direction::Maximise => Box::new(self.agents.iter()),
+            Direction::Minimise => Box::new(self.agents.iter().rev())
         }
     }
 
-    pub fn contains_score(&self, score: Score) -> bool {
-        self.agents.contains_key(&score)
+    pub fn contains_score(&self, score: S) -> bool {
+        self.agents.range((score, 0)..=(score, u64::MAX)).next().is_some()
+    }
+
+    pub fn contains_key(&self, key: AgentKey<S>) -> bool {
+        self.agents.contains_key(&key)
     }
 
     pub fn will_accept(&self, agent: &Agent<Gene>) -> bool {
@@ -150,53 +319,150 @@ impl <Gene> Population <Gene> {
         true
     }
 
-    pub fn get_scores(&self) -> Vec<Score> {
-        self.agents.keys().map(|k| *k).collect()
+    pub fn get_scores(&self) -> Vec<S> {
+        self.agents.keys().map(|(score, _)| *score).collect()
+    }
+
+    pub fn get_keys(&self) -> Vec<AgentKey<S>> {
+        self.agents.keys().cloned().collect()
+    }
+
+    /// Gives every agent a new score, keeping the order in which agents with equal scores were inserted.
+    pub fn rescore<F>(&mut self, mut score: F)
+    where
+    F: FnMut(&AgentKey<S>, &Agent<Gene>) -> S
+    {
+        let agents = std::mem::take(&mut self.agents);
+        for (key, agent) in agents {
+            let (_, id) = key;
+            self.agents.insert((score(&key, &agent), id), agent);
+        }
+    }
+
+    pub fn get_random_key<R: Rng + ?Sized>(&self, rng: &mut R) -> AgentKey<S> {
+        *self.agents.keys().nth(rng.gen_range(0, self.len())).unwrap()
+    }
+
+    pub fn get_random_score<R: Rng + ?Sized>(&self, rng: &mut R) -> S {
+        let (score, _) = self.get_random_key(rng);
+        score
+    }
+
+    fn rebuild_register(&mut self) {
+        if self.unique_agents {
+            self.register.clear();
+            for agent in self.agents.values() {
+                self.register.insert(agent.get_hash());
+            }
+        }
+    }
+}
+
+/// Agents are stored as a list of keys and agents, so that formats like JSON, whose map keys must
+/// be strings, can hold them.
+#[cfg(feature = "serde")]
+impl <Gene, S> Serialize for Population<Gene, S>
+where
+Gene: Serialize,
+S: Score + Serialize
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+    Ser: Serializer
+    {
+        #[derive(Serialize)]
+        struct PopulationData <'a, Gene: 'a, S: 'a> {
+            agents: Vec<(&'a AgentKey<S>, &'a Agent<Gene>)>,
+            unique_agents: bool,
+            max_size: Option<usize>,
+            direction: Direction,
+            next_id: u64
+        }
+
+        PopulationData {
+            agents: self.agents.iter().collect(),
+            unique_agents: self.unique_agents,
+            max_size: self.max_size,
+            direction: self.direction,
+            next_id: self.next_id
+        }.serialize(serializer)
     }
+}
+
+/// The register of unique agents is rebuilt from the agents' genes rather than stored.
+#[cfg(feature = "serde")]
+impl <'de, Gene, S> Deserialize<'de> for Population<Gene, S>
+where
+Gene: Deserialize<'de> + Hash,
+S: Score + Deserialize<'de>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+    D: Deserializer<'de>
+    {
+        #[derive(Deserialize)]
+        #[serde(bound(deserialize = "Gene: Deserialize<'de> + Hash, S: Deserialize<'de>"))]
+        struct PopulationData <Gene, S> {
+            agents: Vec<(AgentKey<S>, Agent<Gene>)>,
+            unique_agents: bool,
+            max_size: Option<usize>,
+            direction: Direction,
+            next_id: u64
+        }
 
-    pub fn get_random_score(&self) -> Score {
-        let mut rng = rand::thread_rng();
-        self.get_scores()[rng.gen_range(0, self.len())]
+        let data = PopulationData::deserialize(deserializer)?;
+        let next_id = data.agents.iter()
+            .map(|((_, id), _)| id + 1)
+            .fold(data.next_id, u64::max);
+        let mut population = Population {
+            agents: data.agents.into_iter().collect(),
+            register: HashSet::new(),
+            unique_agents: data.unique_agents,
+            max_size: data.max_size,
+            direction: data.direction,
+            next_id
+        };
+        population.rebuild_register();
+        Ok(population)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::SeedableRng;
     use super::super::fitness::{GeneralScoreProvider, ScoreError};
 
     #[test]
     fn new_empty() {
         let population: Population<u8> = Population::new_empty(false);
         assert_eq!(0, population.len());
+        assert!(population.is_empty());
         assert_eq!(0, population.get_agents().len());
         assert_eq!(0, population.get_scores().len());
     }
 
-    fn get_score_index(agent: &Agent<u8>, _data: &u8) -> Result<Score, ScoreError> {
-        let score = agent.get_genes()[0] as Score;
+    fn get_score_index(agent: &Agent<u8>, _data: &u8) -> Result<u64, ScoreError> {
+        let score = agent.get_genes()[0] as u64;
         Ok(score)
     }
 
     #[test]
     fn new_with_false_unique() {
-        let mut population = Population::new(5, 6, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25));
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population = Population::new(5, 6, false, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
         assert_eq!(5, population.len());
         assert_eq!(5, population.get_agents().len());
         assert_eq!(5, population.get_scores().len());
-        for (_score, agent) in population.get_agents() {
+        for agent in population.get_agents().values() {
             assert_eq!(6, agent.get_genes().len());
         }
 
-        let random_score = population.get_random_score();
-        let agent = population.get(random_score).unwrap().clone();
+        let random_key = population.get_random_key(&mut rng);
+        let agent = population.get(random_key).unwrap().clone();
         assert!(population.will_accept(&agent));
-        let mut new_score = 0;
-        while population.contains_score(new_score) {
-            new_score += 1;
-        }
 
-        population.insert(new_score, agent);
+        population.insert(0, agent);
         assert_eq!(6, population.len());
         assert_eq!(6, population.get_agents().len());
         assert_eq!(6, population.get_scores().len());
@@ -204,50 +470,125 @@ mod tests {
 
     #[test]
     fn new_with_true_unique() {
-        let mut population = Population::new(5, 6, true, &0, &mut GeneralScoreProvider::new(get_score_index, 25));
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population = Population::new(5, 6, true, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
         assert_eq!(5, population.len());
         assert_eq!(5, population.get_agents().len());
         assert_eq!(5, population.get_scores().len());
-        for (_score, agent) in population.get_agents() {
+        for agent in population.get_agents().values() {
             assert_eq!(6, agent.get_genes().len());
         }
 
-        let random_score = population.get_random_score();
-        let agent = population.get(random_score).unwrap().clone();
+        let random_key = population.get_random_key(&mut rng);
+        let agent = population.get(random_key).unwrap().clone();
         assert!(!population.will_accept(&agent));
-        let mut new_score = 0;
-        while population.contains_score(new_score) {
-            new_score += 1;
-        }
 
-        population.insert(new_score, agent.clone());
+        assert_eq!(None, population.insert(0, agent.clone()));
         assert_eq!(5, population.len());
         assert_eq!(5, population.get_agents().len());
         assert_eq!(5, population.get_scores().len());
 
-        population.remove(random_score);
+        population.remove(random_key);
         assert_eq!(4, population.len());
         assert_eq!(4, population.get_agents().len());
         assert_eq!(4, population.get_scores().len());
 
-        population.insert(new_score, agent);
+        assert!(population.insert(0, agent).is_some());
         assert_eq!(5, population.len());
         assert_eq!(5, population.get_agents().len());
         assert_eq!(5, population.get_scores().len());
     }
 
+    #[test]
+    fn with_sampler_uses_sampled_genes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sampler = |rng: &mut StdRng| rng.gen_range(10, 20);
+        let population = Population::with_sampler(5, 6, false, &sampler, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
+        assert_eq!(5, population.len());
+        for agent in population.get_agents().values() {
+            assert!(agent.get_genes().iter().all(|gene| (10..20).contains(gene)));
+        }
+    }
+
+    #[test]
+    fn insert_retains_agents_with_equal_scores() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(false);
+        let first = population.insert(10, Agent::with_genes(3, &mut rng)).unwrap();
+        let second = population.insert(10, Agent::with_genes(3, &mut rng)).unwrap();
+        let third = population.insert(10, Agent::with_genes(3, &mut rng)).unwrap();
+
+        assert_eq!(3, population.len());
+        assert_eq!(vec![10, 10, 10], population.get_scores());
+        assert_eq!(vec![first, second, third], population.get_keys());
+        assert!(population.contains_score(10));
+        assert!(!population.contains_score(9));
+
+        population.remove(second);
+        assert_eq!(vec![first, third], population.get_keys());
+        assert!(population.contains_score(10));
+    }
+
+    #[test]
+    fn insert_when_full_replaces_lowest() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(false);
+        population.set_max_size(Some(3));
+        for score in &[4, 6, 8] {
+            population.insert(*score, Agent::with_genes(1, &mut rng));
+        }
+
+        // Not higher than the lowest, so turned away.
+        assert_eq!(None, population.insert(4, Agent::with_genes(1, &mut rng)));
+        assert_eq!(vec![4, 6, 8], population.get_scores());
+
+        assert!(population.insert(7, Agent::with_genes(1, &mut rng)).is_some());
+        assert_eq!(vec![6, 7, 8], population.get_scores());
+
+        population.set_max_size(Some(1));
+        assert_eq!(vec![8], population.get_scores());
+    }
+
+    #[test]
+    fn minimise_orders_lowest_first() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(false);
+        population.set_direction(Direction::Minimise);
+        population.set_max_size(Some(3));
+        for score in &[4, 6, 8] {
+            population.insert(*score, Agent::with_genes(1, &mut rng));
+        }
+
+        // Not lower than the worst, so turned away.
+        assert_eq!(None, population.insert(8, Agent::with_genes(1, &mut rng)));
+        assert!(population.insert(5, Agent::with_genes(1, &mut rng)).is_some());
+        assert_eq!(vec![4, 5, 6], population.get_scores());
+
+        assert_eq!(4, population.best_key().unwrap().0);
+        assert_eq!(6, population.worst_key().unwrap().0);
+        let best_first: Vec<u64> = population.iter_best_first().map(|(key, _)| key.0).collect();
+        assert_eq!(vec![4, 5, 6], best_first);
+
+        population.cull_worst(1);
+        assert_eq!(vec![4, 5], population.get_scores());
+        population.cull_best(1);
+        assert_eq!(vec![5], population.get_scores());
+    }
+
     #[test]
     fn cull_all_below() {
-        let mut population = Population::new(5, 6, true, &0, &mut GeneralScoreProvider::new(get_score_index, 25));
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population = Population::new(5, 6, true, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
         assert_eq!(5, population.len());
         assert_eq!(5, population.get_agents().len());
         assert_eq!(5, population.get_scores().len());
 
-        let lowest = population.get_scores()[0];
-        let second_lowest = population.get_scores()[1];
-        let middle = population.get_scores()[2];
-        let second_highest = population.get_scores()[3];
-        let highest = population.get_scores()[4];
+        let keys = population.get_keys();
+        let lowest = keys[0];
+        let second_lowest = keys[1];
+        let middle = keys[2];
+        let second_highest = keys[3];
+        let highest = keys[4];
         
         // Ensure ordering is as expected.
         assert!(highest > lowest);
@@ -261,29 +602,84 @@ mod tests {
         assert_eq!(3, population.get_agents().len());
         assert_eq!(3, population.get_scores().len());
 
-        assert!(!population.contains_score(lowest));
-        assert!(!population.contains_score(second_lowest));
-        assert!(population.contains_score(middle));
-        assert!(population.contains_score(second_highest));
-        assert!(population.contains_score(highest));
-
-        let mut new_score = 0;
-        while population.contains_score(new_score) {
-            new_score += 1;
-        }
+        assert!(!population.contains_key(lowest));
+        assert!(!population.contains_key(second_lowest));
+        assert!(population.contains_key(middle));
+        assert!(population.contains_key(second_highest));
+        assert!(population.contains_key(highest));
 
         // The highest is still in there and so its clone should not be accepted.
         assert!(!population.will_accept(&highest_clone));
-        population.insert(new_score, highest_clone);
+        population.insert(0, highest_clone);
         assert_eq!(3, population.len());
         assert_eq!(3, population.get_agents().len());
         assert_eq!(3, population.get_scores().len());
 
         // The lowest is no longer there and so its clone can be accepted.
         assert!(population.will_accept(&lowest_clone));
-        population.insert(new_score, lowest_clone);
+        population.insert(0, lowest_clone);
         assert_eq!(4, population.len());
         assert_eq!(4, population.get_agents().len());
         assert_eq!(4, population.get_scores().len());
     }
+
+    #[test]
+    fn cull_all_above() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(true);
+        for score in 0..5 {
+            population.insert(score, Agent::with_genes(6, &mut rng));
+        }
+
+        let keys = population.get_keys();
+        let highest_clone = population.get(keys[4]).unwrap().clone();
+
+        population.cull_all_above(keys[2]);
+        assert_eq!(keys[..3].to_vec(), population.get_keys());
+
+        // The highest is no longer there and so its clone can be accepted.
+        assert!(population.will_accept(&highest_clone));
+    }
+
+    #[test]
+    fn cull_random_spares_elites_and_updates_register() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population: Population<u8> = Population::new_empty(true);
+        let mut agents = Vec::new();
+        for score in 0..10 {
+            let agent = Agent::with_genes(4, &mut rng);
+            population.insert(score, agent.clone());
+            agents.push(agent);
+        }
+
+        population.cull_random(7, 3, &mut rng);
+        assert_eq!(vec![7, 8, 9], population.get_scores());
+        for agent in agents.iter().take(7) {
+            assert!(population.will_accept(agent));
+        }
+        for agent in agents.iter().skip(7) {
+            assert!(!population.will_accept(agent));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_keeps_agents_and_rebuilds_register() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut population = Population::new(5, 6, true, &0, &mut GeneralScoreProvider::new(get_score_index, 25), &mut rng);
+        population.set_direction(Direction::Minimise);
+        population.set_max_size(Some(10));
+        let json = ::serde_json::to_string(&population).unwrap();
+
+        let mut loaded: Population<u8> = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(population.get_keys(), loaded.get_keys());
+        assert_eq!(Direction::Minimise, loaded.direction());
+        assert_eq!(Some(10), loaded.max_size());
+        let (key, agent) = population.iter_best_first().next().unwrap();
+        assert!(loaded.get(*key).unwrap().has_same_genes(agent));
+        assert!(!loaded.will_accept(agent));
+
+        let key = loaded.insert(0, Agent::from_genes(vec![0; 6])).unwrap();
+        assert!(!population.contains_key(key));
+    }
 }
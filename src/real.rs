@@ -0,0 +1,758 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::agent::Agent;
+use super::crossover::CrossoverOperator;
+use super::mutation::{MutationOperator, NumericGene};
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use rand::{
+    distributions::{Distribution, Standard, StandardNormal},
+    rngs::StdRng,
+    Rng,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Wraps an f64 so that it can be used as a gene.
+/// Genes are equal, and hash the same, when their bits are the same.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RealGene(pub f64);
+
+impl RealGene {
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl From<f64> for RealGene {
+    fn from(value: f64) -> Self {
+        RealGene(value)
+    }
+}
+
+impl PartialEq for RealGene {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for RealGene {}
+
+impl Hash for RealGene {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl Display for RealGene {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl NumericGene for RealGene {
+    fn to_f64(self) -> f64 {
+        self.0
+    }
+
+    fn from_f64(value: f64) -> Self {
+        RealGene(value)
+    }
+}
+
+/// Draws from [0, 1), like an f64. Use Bounds::random_agent to create agents within their bounds.
+impl Distribution<RealGene> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> RealGene {
+        RealGene(rng.gen())
+    }
+}
+
+/// How a gene that has moved outside of its bounds is brought back inside them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Repair {
+    /// Moves the gene to the nearest bound.
+    #[default]
+    Clamp,
+    /// Reflects the gene back off the bound it passed, by as far as it went past it.
+    Reflect,
+    /// Replaces the gene with a random value within the bounds.
+    Resample
+}
+
+/// The lower and upper bound of each gene of a real valued agent, along with how genes outside
+/// of them are repaired. Repairs with Repair::Clamp by default.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Bounds {
+    ranges: Vec<(f64, f64)>,
+    repair: Repair
+}
+
+impl Bounds {
+    /// Takes the (lower, upper) bounds of each gene, in order.
+    /// Panics if a lower bound is above its upper bound, or either isn't finite.
+    pub fn new(ranges: Vec<(f64, f64)>) -> Self {
+        for (lower, upper) in &ranges {
+            assert!(lower.is_finite() && upper.is_finite(), "bounds must be finite");
+            assert!(lower <= upper, "lower bound must not be above upper bound");
+        }
+
+        Self {
+            ranges,
+            repair: Repair::default()
+        }
+    }
+
+    /// Uses the same bounds for every gene.
+    pub fn uniform(lower: f64, upper: f64, number_of_genes: usize) -> Self {
+        Self::new(vec![(lower, upper); number_of_genes])
+    }
+
+    pub fn set_repair(&mut self, repair: Repair) {
+        self.repair = repair;
+    }
+
+    pub fn repair(&self) -> Repair {
+        self.repair
+    }
+
+    /// The number of genes.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Gets the (lower, upper) bounds of the gene at the index.
+    pub fn range(&self, index: usize) -> (f64, f64) {
+        self.ranges[index]
+    }
+
+    pub fn contains(&self, index: usize, value: f64) -> bool {
+        let (lower, upper) = self.ranges[index];
+        value >= lower && value <= upper
+    }
+
+    /// Creates an agent with a gene for each bound, drawn uniformly from within it.
+    pub fn random_agent<R>(&self, rng: &mut R) -> Agent<RealGene>
+    where
+    R: Rng + ?Sized
+    {
+        let genes = (0..self.len())
+            .map(|index| RealGene(self.random_value(index, rng)))
+            .collect();
+
+        let mut agent = Agent::new();
+        agent.set_genes(genes);
+        agent
+    }
+
+    /// Brings a value for the gene at the index back within its bounds, if it is outside them.
+    pub fn repair_value<R>(&self, index: usize, value: f64, rng: &mut R) -> f64
+    where
+    R: Rng + ?Sized
+    {
+        if self.contains(index, value) {
+            return value;
+        }
+        let (lower, upper) = self.ranges[index];
+        match self.repair {
+            Repair::Clamp => {
+                if value > upper {
+                    upper
+                } else {
+                    lower
+                }
+            },
+            Repair::Reflect => {
+                let width = upper - lower;
+                if width == 0.0 || !value.is_finite() {
+                    return lower;
+                }
+                // Going past both bounds reflects again, so positions repeat every two widths.
+                let mut offset = (value - lower) % (2.0 * width);
+                if offset < 0.0 {
+                    offset += 2.0 * width;
+                }
+                if offset > width {
+                    offset = 2.0 * width - offset;
+                }
+                lower + offset
+            },
+            Repair::Resample => self.random_value(index, rng)
+        }
+    }
+
+    /// Brings every gene back within its bounds. Genes beyond the number of bounds are left alone.
+    pub fn repair_genes<R>(&self, genes: &mut [RealGene], rng: &mut R)
+    where
+    R: Rng + ?Sized
+    {
+        for (index, gene) in genes.iter_mut().enumerate().take(self.len()) {
+            gene.0 = self.repair_value(index, gene.0, rng);
+        }
+    }
+
+    fn random_value<R>(&self, index: usize, rng: &mut R) -> f64
+    where
+    R: Rng + ?Sized
+    {
+        let (lower, upper) = self.ranges[index];
+        if lower == upper {
+            return lower;
+        }
+        rng.gen_range(lower, upper)
+    }
+}
+
+/// Adds normally distributed noise to genes.
+/// The step size is the standard deviation as a proportion of each gene's range, so a step size
+/// of 0.1 on a gene bounded by 0 and 50 has a standard deviation of 5.
+/// Each gene is mutated with a probability of 1 / the number of genes by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GaussianMutation {
+    bounds: Bounds,
+    step_size: f64,
+    probability: Option<f64>
+}
+
+impl GaussianMutation {
+    pub fn new(bounds: Bounds, step_size: f64) -> Self {
+        Self {
+            bounds,
+            step_size,
+            probability: None
+        }
+    }
+
+    pub fn set_step_size(&mut self, step_size: f64) {
+        self.step_size = step_size;
+    }
+
+    pub fn step_size(&self) -> f64 {
+        self.step_size
+    }
+
+    /// Sets the probability of each gene being mutated.
+    pub fn set_probability(&mut self, probability: f64) {
+        self.probability = Some(probability);
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// Returns a mutated copy of the agent, with its genes repaired to be within the bounds.
+    pub fn mutate<R>(&self, agent: &Agent<RealGene>, rng: &mut R) -> Agent<RealGene>
+    where
+    R: Rng + ?Sized
+    {
+        let probability = get_probability(self.probability, &self.bounds);
+        let mut genes = agent.get_genes().clone();
+        for (index, gene) in genes.iter_mut().enumerate().take(self.bounds.len()) {
+            if !rng.gen_bool(probability) {
+                continue;
+            }
+            let (lower, upper) = self.bounds.range(index);
+            let noise: f64 = rng.sample(StandardNormal);
+            gene.0 += noise * self.step_size * (upper - lower);
+        }
+        self.bounds.repair_genes(&mut genes, rng);
+
+        create_child(agent, genes)
+    }
+}
+
+impl MutationOperator<RealGene> for GaussianMutation {
+    fn mutate(&self, agent: &Agent<RealGene>, rng: &mut StdRng) -> Agent<RealGene> {
+        GaussianMutation::mutate(self, agent, rng)
+    }
+}
+
+/// Deb's polynomial mutation, which perturbs genes by an amount that shrinks as it approaches
+/// their bounds, so mutated genes stay within them.
+/// The distribution index controls the step size, with larger values giving smaller steps.
+/// Values of 20 to 100 are common.
+/// Each gene is mutated with a probability of 1 / the number of genes by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolynomialMutation {
+    bounds: Bounds,
+    distribution_index: f64,
+    probability: Option<f64>
+}
+
+impl PolynomialMutation {
+    pub fn new(bounds: Bounds, distribution_index: f64) -> Self {
+        Self {
+            bounds,
+            distribution_index,
+            probability: None
+        }
+    }
+
+    pub fn set_distribution_index(&mut self, distribution_index: f64) {
+        self.distribution_index = distribution_index;
+    }
+
+    pub fn distribution_index(&self) -> f64 {
+        self.distribution_index
+    }
+
+    /// Sets the probability of each gene being mutated.
+    pub fn set_probability(&mut self, probability: f64) {
+        self.probability = Some(probability);
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// Returns a mutated copy of the agent. Genes that started outside of the bounds are repaired.
+    pub fn mutate<R>(&self, agent: &Agent<RealGene>, rng: &mut R) -> Agent<RealGene>
+    where
+    R: Rng + ?Sized
+    {
+        let probability = get_probability(self.probability, &self.bounds);
+        let power = 1.0 / (self.distribution_index + 1.0);
+        let mut genes = agent.get_genes().clone();
+        self.bounds.repair_genes(&mut genes, rng);
+
+        for (index, gene) in genes.iter_mut().enumerate().take(self.bounds.len()) {
+            if !rng.gen_bool(probability) {
+                continue;
+            }
+            let (lower, upper) = self.bounds.range(index);
+            let width = upper - lower;
+            if width == 0.0 {
+                continue;
+            }
+
+            let random: f64 = rng.gen();
+            let delta = if random < 0.5 {
+                let distance = 1.0 - (gene.0 - lower) / width;
+                let value = 2.0 * random + (1.0 - 2.0 * random) * distance.powf(self.distribution_index + 1.0);
+                value.powf(power) - 1.0
+            } else {
+                let distance = 1.0 - (upper - gene.0) / width;
+                let value = 2.0 * (1.0 - random) + 2.0 * (random - 0.5) * distance.powf(self.distribution_index + 1.0);
+                1.0 - value.powf(power)
+            };
+            gene.0 += delta * width;
+        }
+        // Rounding can leave a gene just outside its bounds.
+        self.bounds.repair_genes(&mut genes, rng);
+
+        create_child(agent, genes)
+    }
+}
+
+impl MutationOperator<RealGene> for PolynomialMutation {
+    fn mutate(&self, agent: &Agent<RealGene>, rng: &mut StdRng) -> Agent<RealGene> {
+        PolynomialMutation::mutate(self, agent, rng)
+    }
+}
+
+/// Deb's simulated binary crossover (SBX), which spreads children around the parents' genes like
+/// one-point crossover does for binary genes, narrowing the spread near the bounds.
+/// The distribution index controls the spread, with larger values giving children closer to
+/// their parents. Values of 2 to 20 are common.
+/// Each gene is crossed over with a probability of 0.5 by default, otherwise it is taken from the
+/// first parent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulatedBinaryCrossover {
+    bounds: Bounds,
+    distribution_index: f64,
+    probability: f64
+}
+
+impl SimulatedBinaryCrossover {
+    pub fn new(bounds: Bounds, distribution_index: f64) -> Self {
+        Self {
+            bounds,
+            distribution_index,
+            probability: 0.5
+        }
+    }
+
+    pub fn set_distribution_index(&mut self, distribution_index: f64) {
+        self.distribution_index = distribution_index;
+    }
+
+    pub fn distribution_index(&self) -> f64 {
+        self.distribution_index
+    }
+
+    /// Sets the probability of each gene being crossed over.
+    pub fn set_probability(&mut self, probability: f64) {
+        self.probability = probability;
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// Creates one of the two children SBX produces, chosen at random.
+    /// Keeps the number of genes of the first parent.
+    pub fn crossover<R>(&self, parent1: &Agent<RealGene>, parent2: &Agent<RealGene>, rng: &mut R) -> Agent<RealGene>
+    where
+    R: Rng + ?Sized
+    {
+        let probability = self.probability.clamp(0.0, 1.0);
+        let power = 1.0 / (self.distribution_index + 1.0);
+        let mut genes = parent1.get_genes().clone();
+
+        for (index, (gene, other)) in genes.iter_mut().zip(parent2.get_genes()).enumerate().take(self.bounds.len()) {
+            if !rng.gen_bool(probability) || (gene.0 - other.0).abs() <= f64::EPSILON {
+                continue;
+            }
+            let (lower, upper) = self.bounds.range(index);
+            let smaller = gene.0.min(other.0);
+            let larger = gene.0.max(other.0);
+            let difference = larger - smaller;
+
+            let random: f64 = rng.gen();
+            let spread = |distance: f64| {
+                let beta = 1.0 + 2.0 * distance / difference;
+                let alpha = 2.0 - beta.powf(-(self.distribution_index + 1.0));
+                if random <= 1.0 / alpha {
+                    (random * alpha).powf(power)
+                } else {
+                    (1.0 / (2.0 - random * alpha)).powf(power)
+                }
+            };
+
+            gene.0 = if rng.gen() {
+                0.5 * (smaller + larger - spread(smaller - lower) * difference)
+            } else {
+                0.5 * (smaller + larger + spread(upper - larger) * difference)
+            };
+        }
+        self.bounds.repair_genes(&mut genes, rng);
+
+        create_child(parent1, genes)
+    }
+}
+
+impl CrossoverOperator<RealGene> for SimulatedBinaryCrossover {
+    fn crossover(&self, parent1: &Agent<RealGene>, parent2: &Agent<RealGene>, rng: &mut StdRng) -> Agent<RealGene> {
+        SimulatedBinaryCrossover::crossover(self, parent1, parent2, rng)
+    }
+}
+
+/// Blend crossover (BLX-alpha), which draws each gene uniformly from the range between the
+/// parents' genes, extended by alpha times that range on each side.
+/// An alpha of 0.5 is common. Genes that land outside of the bounds are repaired.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlendCrossover {
+    bounds: Bounds,
+    alpha: f64
+}
+
+impl BlendCrossover {
+    pub fn new(bounds: Bounds, alpha: f64) -> Self {
+        Self {
+            bounds,
+            alpha
+        }
+    }
+
+    pub fn set_alpha(&mut self, alpha: f64) {
+        self.alpha = alpha;
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// Keeps the number of genes of the first parent.
+    pub fn crossover<R>(&self, parent1: &Agent<RealGene>, parent2: &Agent<RealGene>, rng: &mut R) -> Agent<RealGene>
+    where
+    R: Rng + ?Sized
+    {
+        let mut genes = parent1.get_genes().clone();
+
+        for (gene, other) in genes.iter_mut().zip(parent2.get_genes()).take(self.bounds.len()) {
+            let extension = self.alpha * (gene.0 - other.0).abs();
+            let lower = gene.0.min(other.0) - extension;
+            let upper = gene.0.max(other.0) + extension;
+            if lower < upper {
+                gene.0 = rng.gen_range(lower, upper);
+            }
+        }
+        self.bounds.repair_genes(&mut genes, rng);
+
+        create_child(parent1, genes)
+    }
+}
+
+impl CrossoverOperator<RealGene> for BlendCrossover {
+    fn crossover(&self, parent1: &Agent<RealGene>, parent2: &Agent<RealGene>, rng: &mut StdRng) -> Agent<RealGene> {
+        BlendCrossover::crossover(self, parent1, parent2, rng)
+    }
+}
+
+/// Whole arithmetic crossover, which makes each gene the weighted average of the parents' genes,
+/// with the first parent's gene weighted by the weight and the second's by 1 - weight.
+/// A new random weight between 0 and 1 is used for each child by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArithmeticCrossover {
+    bounds: Bounds,
+    weight: Option<f64>
+}
+
+impl ArithmeticCrossover {
+    pub fn new(bounds: Bounds) -> Self {
+        Self {
+            bounds,
+            weight: None
+        }
+    }
+
+    /// Uses the same weight for every child, such as 0.5 for the midpoint of the parents.
+    pub fn set_weight(&mut self, weight: f64) {
+        self.weight = Some(weight);
+    }
+
+    pub fn weight(&self) -> Option<f64> {
+        self.weight
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// Keeps the number of genes of the first parent.
+    pub fn crossover<R>(&self, parent1: &Agent<RealGene>, parent2: &Agent<RealGene>, rng: &mut R) -> Agent<RealGene>
+    where
+    R: Rng + ?Sized
+    {
+        let weight = match self.weight {
+            Some(weight) => weight,
+            None => rng.gen()
+        };
+        let mut genes = parent1.get_genes().clone();
+
+        for (gene, other) in genes.iter_mut().zip(parent2.get_genes()).take(self.bounds.len()) {
+            gene.0 = weight * gene.0 + (1.0 - weight) * other.0;
+        }
+        self.bounds.repair_genes(&mut genes, rng);
+
+        create_child(parent1, genes)
+    }
+}
+
+impl CrossoverOperator<RealGene> for ArithmeticCrossover {
+    fn crossover(&self, parent1: &Agent<RealGene>, parent2: &Agent<RealGene>, rng: &mut StdRng) -> Agent<RealGene> {
+        ArithmeticCrossover::crossover(self, parent1, parent2, rng)
+    }
+}
+
+fn get_probability(probability: Option<f64>, bounds: &Bounds) -> f64 {
+    let probability = probability.unwrap_or(1.0 / bounds.len().max(1) as f64);
+    probability.clamp(0.0, 1.0)
+}
+
+fn create_child(parent: &Agent<RealGene>, genes: Vec<RealGene>) -> Agent<RealGene> {
+    let mut child = parent.clone();
+    child.set_genes(genes);
+    child
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn bounds() -> Bounds {
+        Bounds::new(vec![(0.0, 1.0), (-5.0, 5.0), (10.0, 100.0), (2.0, 2.0)])
+    }
+
+    fn is_within(bounds: &Bounds, agent: &Agent<RealGene>) -> bool {
+        agent.get_genes().iter().enumerate().all(|(index, gene)| bounds.contains(index, gene.0))
+    }
+
+    #[test]
+    fn random_agent_is_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..50 {
+            let agent = bounds().random_agent(&mut rng);
+            assert_eq!(4, agent.get_genes().len());
+            assert!(is_within(&bounds(), &agent));
+        }
+    }
+
+    #[test]
+    fn repair_clamps_to_nearest_bound() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let bounds = bounds();
+        assert_eq!(5.0, bounds.repair_value(1, 7.0, &mut rng));
+        assert_eq!(-5.0, bounds.repair_value(1, -7.0, &mut rng));
+        assert_eq!(3.0, bounds.repair_value(1, 3.0, &mut rng));
+    }
+
+    #[test]
+    fn repair_reflects_off_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut bounds = bounds();
+        bounds.set_repair(Repair::Reflect);
+        assert_eq!(3.0, bounds.repair_value(1, 7.0, &mut rng));
+        assert_eq!(-3.0, bounds.repair_value(1, -7.0, &mut rng));
+        assert_eq!(-1.0, bounds.repair_value(1, 19.0, &mut rng));
+        assert_eq!(2.0, bounds.repair_value(3, 3.0, &mut rng));
+    }
+
+    #[test]
+    fn repair_resamples_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut bounds = bounds();
+        bounds.set_repair(Repair::Resample);
+        for _ in 0..50 {
+            let value = bounds.repair_value(2, 1000.0, &mut rng);
+            assert!(bounds.contains(2, value));
+        }
+    }
+
+    #[test]
+    fn gaussian_mutation_stays_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut mutation = GaussianMutation::new(bounds(), 0.5);
+        mutation.set_probability(1.0);
+        let agent = bounds().random_agent(&mut rng);
+        for _ in 0..50 {
+            let child = mutation.mutate(&agent, &mut rng);
+            assert!(is_within(&bounds(), &child));
+            assert!(!child.has_same_genes(&agent));
+        }
+    }
+
+    #[test]
+    fn polynomial_mutation_stays_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut mutation = PolynomialMutation::new(bounds(), 20.0);
+        mutation.set_probability(1.0);
+        let agent = bounds().random_agent(&mut rng);
+        for _ in 0..50 {
+            let child = mutation.mutate(&agent, &mut rng);
+            assert!(is_within(&bounds(), &child));
+            assert!(!child.has_same_genes(&agent));
+        }
+    }
+
+    #[test]
+    fn crossovers_stay_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut simulated_binary = SimulatedBinaryCrossover::new(bounds(), 2.0);
+        simulated_binary.set_probability(1.0);
+        let blend = BlendCrossover::new(bounds(), 0.5);
+        let arithmetic = ArithmeticCrossover::new(bounds());
+
+        for _ in 0..50 {
+            let parent1 = bounds().random_agent(&mut rng);
+            let parent2 = bounds().random_agent(&mut rng);
+            assert!(is_within(&bounds(), &simulated_binary.crossover(&parent1, &parent2, &mut rng)));
+            assert!(is_within(&bounds(), &blend.crossover(&parent1, &parent2, &mut rng)));
+            assert!(is_within(&bounds(), &arithmetic.crossover(&parent1, &parent2, &mut rng)));
+        }
+    }
+
+    #[test]
+    fn crossovers_of_identical_parents_return_same_genes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut simulated_binary = SimulatedBinaryCrossover::new(bounds(), 2.0);
+        simulated_binary.set_probability(1.0);
+        let blend = BlendCrossover::new(bounds(), 0.5);
+        let parent = bounds().random_agent(&mut rng);
+
+        assert!(simulated_binary.crossover(&parent, &parent, &mut rng).has_same_genes(&parent));
+        assert!(blend.crossover(&parent, &parent, &mut rng).has_same_genes(&parent));
+    }
+
+    #[test]
+    fn simulated_binary_crossover_spreads_around_parents() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let bounds = Bounds::uniform(-1000.0, 1000.0, 1);
+        let mut crossover = SimulatedBinaryCrossover::new(bounds.clone(), 2.0);
+        crossover.set_probability(1.0);
+        let mut parent1 = Agent::new();
+        parent1.set_genes(vec![RealGene(-1.0)]);
+        let mut parent2 = Agent::new();
+        parent2.set_genes(vec![RealGene(1.0)]);
+
+        let mut inside = 0;
+        for _ in 0..100 {
+            let gene = crossover.crossover(&parent1, &parent2, &mut rng).get_genes()[0].0;
+            if gene > -1.0 && gene < 1.0 {
+                inside += 1;
+            }
+        }
+        // Half of the children fall between the parents.
+        assert!(inside > 30 && inside < 70);
+    }
+
+    #[test]
+    fn crossovers_can_be_used_as_operators() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let operators: Vec<Box<dyn CrossoverOperator<RealGene>>> = vec![
+            Box::new(SimulatedBinaryCrossover::new(bounds(), 2.0)),
+            Box::new(BlendCrossover::new(bounds(), 0.5)),
+            Box::new(ArithmeticCrossover::new(bounds()))
+        ];
+
+        for operator in operators {
+            let parent1 = bounds().random_agent(&mut rng);
+            let parent2 = bounds().random_agent(&mut rng);
+            assert!(is_within(&bounds(), &operator.crossover(&parent1, &parent2, &mut rng)));
+        }
+    }
+
+    #[test]
+    fn arithmetic_crossover_with_weight_averages_parents() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut crossover = ArithmeticCrossover::new(Bounds::uniform(0.0, 10.0, 2));
+        crossover.set_weight(0.5);
+        let mut parent1 = Agent::new();
+        parent1.set_genes(vec![RealGene(2.0), RealGene(4.0)]);
+        let mut parent2 = Agent::new();
+        parent2.set_genes(vec![RealGene(6.0), RealGene(10.0)]);
+
+        let child = crossover.crossover(&parent1, &parent2, &mut rng);
+        assert_eq!(&vec![RealGene(4.0), RealGene(7.0)], child.get_genes());
+    }
+
+    #[test]
+    fn larger_step_sizes_move_genes_further() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let bounds = Bounds::uniform(-1000.0, 1000.0, 1);
+        let agent = Bounds::uniform(0.0, 0.0, 1).random_agent(&mut rng);
+        let small = GaussianMutation::new(bounds.clone(), 0.001);
+        let large = GaussianMutation::new(bounds, 0.1);
+
+        let mut small_total = 0.0;
+        let mut large_total = 0.0;
+        for _ in 0..50 {
+            small_total += small.mutate(&agent, &mut rng).get_genes()[0].0.abs();
+            large_total += large.mutate(&agent, &mut rng).get_genes()[0].0.abs();
+        }
+        assert!(large_total > small_total * 10.0);
+    }
+}
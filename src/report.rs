@@ -0,0 +1,167 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::agent::Agent;
+use super::fitness::{Score, ScoreProvider};
+use super::island::IslandStats;
+use super::population::Population;
+use super::termination::StopCondition;
+use std::time::Duration;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The best and mean scores of the main population after a generation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GenerationStats <S = u64> {
+    generation: usize,
+    best: S,
+    mean: f64
+}
+
+impl <S> GenerationStats <S>
+where
+S: Score
+{
+    pub(crate) fn new(generation: usize, best: S, mean: f64) -> Self {
+        Self {
+            generation,
+            best,
+            mean
+        }
+    }
+
+    /// Returns None if the population is empty.
+    pub fn from_population<Gene>(generation: usize, population: &Population<Gene, S>) -> Option<Self> {
+        let (best, _) = population.best_key()?;
+        let scores = population.get_scores();
+        let mean = scores.iter().map(|score| score.to_f64()).sum::<f64>() / scores.len() as f64;
+        Some(Self::new(generation, best, mean))
+    }
+
+    /// Like from_population, but with the scores from the score provider's fitness, which leave
+    /// out the random offset of the population's scores.
+    pub fn from_fitness<Gene, Data, SP>(generation: usize, population: &Population<Gene, S>, score_provider: &SP) -> Option<Self>
+    where
+    SP: ScoreProvider<Gene, Data, S> + ?Sized
+    {
+        let (best, _) = population.best_fitness(score_provider)?;
+        let total: f64 = population.get_agents().iter()
+            .map(|((score, _), agent)| score_provider.fitness(agent).unwrap_or(*score).to_f64())
+            .sum();
+        Some(Self::new(generation, best, total / population.len() as f64))
+    }
+
+    /// The number of iterations run before these stats were taken. The initial population is
+    /// generation 0.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn best(&self) -> S {
+        self.best
+    }
+
+    /// The mean of the scores, from Score::to_f64.
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+}
+
+/// A summary of a run, returned by Manager::run and Manager::run_until.
+/// With the serde feature, it can be serialized to keep a record of the run.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RunReport <Gene, S = u64> {
+    pub(crate) best_agent: Option<Agent<Gene>>,
+    pub(crate) best_score: Option<S>,
+    pub(crate) generations: usize,
+    pub(crate) evaluations: u64,
+    pub(crate) elapsed: Duration,
+    pub(crate) stop_reason: StopCondition<S>,
+    pub(crate) history: Vec<GenerationStats<S>>,
+    pub(crate) islands: Vec<IslandStats<S>>
+}
+
+impl <Gene, S> RunReport <Gene, S>
+where
+S: Score
+{
+    /// The best agent of the main population at the end of the run. None if it was empty.
+    pub fn best_agent(&self) -> Option<&Agent<Gene>> {
+        self.best_agent.as_ref()
+    }
+
+    /// The best agent's fitness from the score provider, without the random offset of the
+    /// population's scores.
+    pub fn best_score(&self) -> Option<S> {
+        self.best_score
+    }
+
+    /// The number of iterations the main population ran.
+    pub fn generations(&self) -> usize {
+        self.generations
+    }
+
+    /// The number of calls to the fitness function, including by child threads that reported back.
+    pub fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The condition that stopped the run. For StopCondition::Any, that is the first of its
+    /// conditions that was met.
+    pub fn stop_reason(&self) -> &StopCondition<S> {
+        &self.stop_reason
+    }
+
+    /// The stats of the main population after each generation, starting with the initial
+    /// population. Agents added by child threads show up in the generation after they arrive.
+    /// With islands, there is an entry for all of the islands together after each migration.
+    /// The scores are the score provider's fitness, without the random offset.
+    pub fn history(&self) -> &[GenerationStats<S>] {
+        &self.history
+    }
+
+    /// The stats of each island at the end of the run. Empty unless the manager ran islands.
+    pub fn islands(&self) -> &[IslandStats<S>] {
+        &self.islands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::fitness::Direction;
+
+    #[test]
+    fn generation_stats_follow_direction() {
+        let mut population: Population<u8> = Population::new_empty(false);
+        assert!(GenerationStats::from_population(0, &population).is_none());
+
+        population.insert(2, Agent::from_genes(vec![2]));
+        population.insert(4, Agent::from_genes(vec![4]));
+        population.insert(9, Agent::from_genes(vec![9]));
+        let stats = GenerationStats::from_population(3, &population).unwrap();
+        assert_eq!(3, stats.generation());
+        assert_eq!(9, stats.best());
+        assert_eq!(5.0, stats.mean());
+
+        population.set_direction(Direction::Minimise);
+        assert_eq!(2, GenerationStats::from_population(3, &population).unwrap().best());
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rand::{
+    distributions::{
+        uniform::SampleUniform,
+        Distribution,
+        Standard,
+        Uniform,
+        WeightedError,
+        WeightedIndex
+    },
+    rngs::StdRng,
+    Rng,
+};
+
+/// Creates random genes, for new agents and for mutations that add genes.
+/// Implemented for any closure that takes the StdRng and returns a gene.
+pub trait GeneSampler <Gene>: Send + Sync {
+    fn sample(&self, rng: &mut StdRng) -> Gene;
+}
+
+impl <Gene, F> GeneSampler<Gene> for F
+where
+F: Fn(&mut StdRng) -> Gene + Send + Sync
+{
+    fn sample(&self, rng: &mut StdRng) -> Gene {
+        self(rng)
+    }
+}
+
+/// Samples genes from the Standard distribution, for gene types that implement it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StandardSampler;
+
+impl <Gene> GeneSampler<Gene> for StandardSampler
+where
+Standard: Distribution<Gene>
+{
+    fn sample(&self, rng: &mut StdRng) -> Gene {
+        rng.gen()
+    }
+}
+
+/// Chooses each gene from a set of genes, with equal chances.
+#[derive(Clone, Debug)]
+pub struct ChoiceSampler <Gene> {
+    genes: Vec<Gene>
+}
+
+impl <Gene> ChoiceSampler <Gene>
+where
+Gene: Clone
+{
+    /// Panics if there are no genes to choose from.
+    pub fn new(genes: &[Gene]) -> Self {
+        assert!(!genes.is_empty(), "there must be at least one gene to choose from");
+        Self {
+            genes: genes.to_vec()
+        }
+    }
+
+    pub fn genes(&self) -> &[Gene] {
+        &self.genes
+    }
+}
+
+impl <Gene> GeneSampler<Gene> for ChoiceSampler <Gene>
+where
+Gene: Clone + Send + Sync
+{
+    fn sample(&self, rng: &mut StdRng) -> Gene {
+        self.genes[rng.gen_range(0, self.genes.len())].clone()
+    }
+}
+
+/// Chooses each gene from a set of genes, with chances proportional to their weights.
+#[derive(Clone, Debug)]
+pub struct WeightedSampler <Gene> {
+    genes: Vec<Gene>,
+    index: WeightedIndex<f64>
+}
+
+impl <Gene> WeightedSampler <Gene> {
+    /// Takes each gene with its weight. Returns an error if there are no genes, a weight is
+    /// negative, or every weight is zero.
+    pub fn new(weighted_genes: Vec<(Gene, f64)>) -> Result<Self, WeightedError> {
+        let (genes, weights): (Vec<Gene>, Vec<f64>) = weighted_genes.into_iter().unzip();
+        let index = WeightedIndex::new(weights)?;
+        Ok(Self {
+            genes,
+            index
+        })
+    }
+
+    pub fn genes(&self) -> &[Gene] {
+        &self.genes
+    }
+}
+
+impl <Gene> GeneSampler<Gene> for WeightedSampler <Gene>
+where
+Gene: Clone + Send + Sync
+{
+    fn sample(&self, rng: &mut StdRng) -> Gene {
+        self.genes[self.index.sample(rng)].clone()
+    }
+}
+
+/// Draws each gene uniformly from a range of integers or floats.
+pub struct RangeSampler <Gene>
+where
+Gene: SampleUniform
+{
+    uniform: Uniform<Gene>
+}
+
+impl <Gene> RangeSampler <Gene>
+where
+Gene: SampleUniform
+{
+    /// Draws from low up to, but not including, high. Panics if low isn't below high.
+    pub fn new(low: Gene, high: Gene) -> Self {
+        Self {
+            uniform: Uniform::new(low, high)
+        }
+    }
+
+    /// Draws from low up to and including high. Panics if low is above high.
+    pub fn new_inclusive(low: Gene, high: Gene) -> Self {
+        Self {
+            uniform: Uniform::new_inclusive(low, high)
+        }
+    }
+}
+
+impl <Gene> Clone for RangeSampler <Gene>
+where
+Gene: SampleUniform,
+Uniform<Gene>: Clone
+{
+    fn clone(&self) -> Self {
+        Self {
+            uniform: self.uniform.clone()
+        }
+    }
+}
+
+impl <Gene> GeneSampler<Gene> for RangeSampler <Gene>
+where
+Gene: SampleUniform,
+Uniform<Gene>: Send + Sync
+{
+    fn sample(&self, rng: &mut StdRng) -> Gene {
+        self.uniform.sample(rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn choice_sampler_uses_every_gene() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sampler = ChoiceSampler::new(&['a', 'b', 'c']);
+        let genes: Vec<char> = (0..50).map(|_| sampler.sample(&mut rng)).collect();
+        assert!(genes.iter().all(|gene| sampler.genes().contains(gene)));
+        assert!(sampler.genes().iter().all(|gene| genes.contains(gene)));
+    }
+
+    #[test]
+    fn weighted_sampler_follows_weights() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sampler = WeightedSampler::new(vec![('a', 0.0), ('b', 9.0), ('c', 1.0)]).unwrap();
+        let genes: Vec<char> = (0..100).map(|_| sampler.sample(&mut rng)).collect();
+        assert!(!genes.contains(&'a'));
+        let b = genes.iter().filter(|gene| **gene == 'b').count();
+        assert!(b > 75);
+    }
+
+    #[test]
+    fn weighted_sampler_rejects_zero_weights() {
+        assert!(WeightedSampler::new(vec![('a', 0.0)]).is_err());
+        assert!(WeightedSampler::<char>::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn range_sampler_stays_in_range() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let integers = RangeSampler::new_inclusive(-3, 3);
+        let floats = RangeSampler::new(0.5, 1.5);
+        for _ in 0..50 {
+            let integer: i32 = integers.sample(&mut rng);
+            assert!((-3..=3).contains(&integer));
+            let float: f64 = floats.sample(&mut rng);
+            assert!((0.5..1.5).contains(&float));
+        }
+    }
+
+    #[test]
+    fn closures_are_samplers() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sampler = |rng: &mut StdRng| rng.gen_range(10u8, 20);
+        assert!((10..20).contains(&sampler.sample(&mut rng)));
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2019 Brendan Cox
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::fitness::{Direction, Score};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+
+/// A condition for stopping Manager::run_until.
+/// Conditions are checked after each cycle, so a run can go past a limit by up to one cycle.
+/// With the serde feature, it can be serialized to record why a run stopped. Cancelled is stored
+/// as the value of its flag.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum StopCondition <S = u64> {
+    /// Stops when the best agent has reached the score. That is, a score of at least the goal
+    /// when maximising, or at most the goal when minimising.
+    Goal(S),
+    /// Stops once the main population has run this many iterations.
+    MaxGenerations(usize),
+    /// Stops once the run has taken this long.
+    TimeLimit(Duration),
+    /// Stops once the fitness function has been called this many times, including by child threads.
+    MaxEvaluations(u64),
+    /// Stops when the best score hasn't improved for this many cycles.
+    Stagnation(usize),
+    /// Stops once the flag is set, for example from another thread.
+    Cancelled(#[cfg_attr(feature = "serde", serde(serialize_with = "serialize_flag"))] Arc<AtomicBool>),
+    /// Stops when any of the conditions is met.
+    Any(Vec<StopCondition<S>>),
+    /// Stops when all of the conditions are met.
+    All(Vec<StopCondition<S>>)
+}
+
+impl <S> StopCondition <S>
+where
+S: Score
+{
+    /// Returns a condition that stops when either condition is met.
+    pub fn or(self, other: Self) -> Self {
+        match self {
+            StopCondition::Any(mut conditions) => {
+                conditions.push(other);
+                StopCondition::Any(conditions)
+            },
+            condition => StopCondition::Any(vec![condition, other])
+        }
+    }
+
+    /// Returns a condition that stops when both conditions are met.
+    pub fn and(self, other: Self) -> Self {
+        match self {
+            StopCondition::All(mut conditions) => {
+                conditions.push(other);
+                StopCondition::All(conditions)
+            },
+            condition => StopCondition::All(vec![condition, other])
+        }
+    }
+
+    /// Returns the condition that has been met, if any. For Any, this is the first of its
+    /// conditions that has been met, and for All it is the All condition itself.
+    pub(crate) fn check(&self, progress: &RunProgress<S>) -> Option<&Self> {
+        let met = match self {
+            StopCondition::Goal(goal) => progress.best.is_some_and(|best| progress.direction.has_reached(best, *goal)),
+            StopCondition::MaxGenerations(generations) => progress.generations >= *generations,
+            StopCondition::TimeLimit(duration) => progress.elapsed >= *duration,
+            StopCondition::MaxEvaluations(evaluations) => progress.evaluations >= *evaluations,
+            StopCondition::Stagnation(cycles) => progress.cycles_without_improvement >= *cycles,
+            StopCondition::Cancelled(flag) => flag.load(Ordering::SeqCst),
+            StopCondition::Any(conditions) => {
+                return conditions.iter().find_map(|condition| condition.check(progress));
+            },
+            StopCondition::All(conditions) => conditions.iter().all(|condition| condition.check(progress).is_some())
+        };
+
+        if met {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn serialize_flag<Ser>(flag: &Arc<AtomicBool>, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+where
+Ser: Serializer
+{
+    serializer.serialize_bool(flag.load(Ordering::SeqCst))
+}
+
+/// How far a run has got, for checking stop conditions.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RunProgress <S> {
+    pub(crate) direction: Direction,
+    pub(crate) best: Option<S>,
+    pub(crate) generations: usize,
+    pub(crate) cycles_without_improvement: usize,
+    pub(crate) evaluations: u64,
+    pub(crate) elapsed: Duration
+}
+
+impl <S> RunProgress <S>
+where
+S: Score
+{
+    pub(crate) fn new(direction: Direction) -> Self {
+        Self {
+            direction,
+            best: None,
+            generations: 0,
+            cycles_without_improvement: 0,
+            evaluations: 0,
+            elapsed: Duration::from_secs(0)
+        }
+    }
+
+    /// Records a cycle of the given number of iterations, and the best score after it.
+    pub(crate) fn add_cycle(&mut self, iterations: usize, best: Option<S>) {
+        self.generations += iterations;
+        let improved = match (self.best, best) {
+            (Some(previous), Some(best)) => self.direction.is_better(best, previous),
+            (None, Some(_)) => true,
+            _ => false
+        };
+        if improved {
+            self.best = best;
+            self.cycles_without_improvement = 0;
+        } else {
+            self.cycles_without_improvement += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stagnation_counts_cycles_without_improvement() {
+        let mut progress = RunProgress::new(Direction::Minimise);
+        let condition = StopCondition::Stagnation(2);
+        progress.add_cycle(10, Some(5u64));
+        progress.add_cycle(10, Some(5));
+        assert!(condition.check(&progress).is_none());
+        progress.add_cycle(10, Some(4));
+        progress.add_cycle(10, Some(4));
+        assert!(condition.check(&progress).is_none());
+        progress.add_cycle(10, Some(6));
+        assert!(condition.check(&progress).is_some());
+        assert_eq!(50, progress.generations);
+        assert_eq!(Some(4), progress.best);
+    }
+
+    #[test]
+    fn any_returns_the_condition_met() {
+        let mut progress = RunProgress::new(Direction::Maximise);
+        progress.add_cycle(100, Some(10u64));
+        let condition = StopCondition::Goal(20).or(StopCondition::MaxGenerations(100));
+        match condition.check(&progress) {
+            Some(StopCondition::MaxGenerations(100)) => {},
+            met => panic!("unexpected condition {:?}", met)
+        }
+    }
+
+    #[test]
+    fn all_needs_every_condition() {
+        let mut progress = RunProgress::new(Direction::Maximise);
+        progress.add_cycle(100, Some(10u64));
+        progress.evaluations = 50;
+        let condition = StopCondition::MaxGenerations(100).and(StopCondition::MaxEvaluations(60));
+        assert!(condition.check(&progress).is_none());
+        progress.evaluations = 60;
+        match condition.check(&progress) {
+            Some(StopCondition::All(conditions)) => assert_eq!(2, conditions.len()),
+            met => panic!("unexpected condition {:?}", met)
+        }
+    }
+
+    #[test]
+    fn cancelled_when_flag_set() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let condition: StopCondition = StopCondition::Cancelled(flag.clone());
+        let progress = RunProgress::new(Direction::Maximise);
+        assert!(condition.check(&progress).is_none());
+        flag.store(true, Ordering::SeqCst);
+        assert!(condition.check(&progress).is_some());
+    }
+}